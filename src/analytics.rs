@@ -0,0 +1,336 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::readback::Readback;
+use crate::simulation::Simulation;
+
+// How the flock as a whole is doing after a step. The layout matches `analytics.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FlockMetrics {
+    pub centroid: [f32; 2],
+    // length of the mean heading, 1 when every boid flies the same way
+    pub polarization: f32,
+    // mean heading around the centroid, 1 when every boid circles it the same way
+    pub milling: f32,
+    // mean distance from each boid to the closest other one
+    pub nearest_neighbour: f32,
+    // root mean square distance from the centroid
+    pub radius_of_gyration: f32,
+    // groups of boids joined by chains of neighbours within the flock radius, counting
+    // lone boids as groups of one
+    pub clusters: u32,
+    pub population: u32,
+}
+
+impl FlockMetrics {
+    pub const CSV_COLUMNS: &'static str = "population,polarization,milling,nearest_neighbour,centroid_x,centroid_y,radius_of_gyration,clusters";
+
+    // The metrics as CSV fields, in the order of `CSV_COLUMNS`.
+    pub fn csv_fields(&self) -> String {
+        let [x, y] = self.centroid;
+        format!(
+            "{},{},{},{},{x},{y},{},{}",
+            self.population,
+            self.polarization,
+            self.milling,
+            self.nearest_neighbour,
+            self.radius_of_gyration,
+            self.clusters,
+        )
+    }
+}
+
+// Steps whose metrics are read back together.
+const BATCH: usize = 60;
+// Steps kept for the graphs in the HUD.
+pub const HISTORY: usize = 120;
+
+
+// Measures the flock after every step on the GPU. The metrics are gathered into a batch
+// there and read back a batch at a time, so none are missed while a read is in flight,
+// and then kept for the HUD and written out as CSV. Finding neighbours and clusters
+// checks every pair of boids, which costs about as much as a step.
+pub struct Analytics {
+    capacity: u32,
+
+    metrics_buffer: wgpu::Buffer,
+    slot_buffer: wgpu::Buffer,
+    bind_groups: Vec<wgpu::BindGroup>,
+    neighbours_pipeline: wgpu::ComputePipeline,
+    clusters_pipeline: wgpu::ComputePipeline,
+    moments_pipeline: wgpu::ComputePipeline,
+    spread_pipeline: wgpu::ComputePipeline,
+
+    // the frames whose metrics are in the batch on the GPU, in order
+    pending: Vec<usize>,
+    // the frames whose metrics are being read back
+    copied: Vec<usize>,
+    readback: Readback,
+
+    // the most recent metrics, oldest first, with the frame they're for
+    history: VecDeque<(usize, FlockMetrics)>,
+    csv: Option<BufWriter<File>>,
+}
+
+impl Analytics {
+    pub fn new(device: &wgpu::Device, simulation: &Simulation) -> Self {
+        let capacity = simulation.capacity as u32;
+        let metrics_size = (BATCH * std::mem::size_of::<FlockMetrics>()) as wgpu::BufferAddress;
+
+        let nearest_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Nearest Neighbour Buffer"),
+                size: capacity as wgpu::BufferAddress * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }
+        );
+        let parents_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Cluster Parents Buffer"),
+                size: capacity as wgpu::BufferAddress * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }
+        );
+        let metrics_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Flock Metrics Buffer"),
+                size: metrics_size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }
+        );
+        let slot_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Flock Metrics Slot Buffer"),
+                size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Analytics Bind Group Layout"),
+                entries: &[
+                    storage_entry(0, true),
+                    storage_entry(1, true),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    storage_entry(3, false),
+                    storage_entry(4, false),
+                    storage_entry(5, false),
+                    storage_entry(6, false),
+                ],
+            }
+        );
+
+        // bind group `i` measures `boids_buffers[i]`
+        let mut bind_groups = Vec::new();
+        for (i, boids_buffer) in simulation.boids_buffers.iter().enumerate() {
+            let bind_group = device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    label: Some(format!("Analytics Bind Group {}", i).as_str()),
+                    layout: &bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: boids_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: simulation.population_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: simulation.params_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: nearest_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: parents_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: metrics_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 6,
+                            resource: slot_buffer.as_entire_binding(),
+                        },
+                    ],
+                }
+            );
+            bind_groups.push(bind_group);
+        }
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("analytics.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Analytics Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            }
+        );
+        let create_pipeline = |label, entry_point| device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            }
+        );
+
+        Self {
+            capacity,
+
+            metrics_buffer,
+            slot_buffer,
+            bind_groups,
+            neighbours_pipeline: create_pipeline("Neighbours Pipeline", "cs_neighbours"),
+            clusters_pipeline: create_pipeline("Clusters Pipeline", "cs_clusters"),
+            moments_pipeline: create_pipeline("Moments Pipeline", "cs_moments"),
+            spread_pipeline: create_pipeline("Spread Pipeline", "cs_spread"),
+
+            pending: Vec::with_capacity(BATCH),
+            copied: Vec::new(),
+            readback: Readback::new(device, "Flock Metrics Readback Buffer", metrics_size),
+
+            history: VecDeque::with_capacity(HISTORY),
+            csv: None,
+        }
+    }
+
+    // Writes every step's metrics to a CSV file at `path` from now on.
+    pub fn stream_to(&mut self, path: &str) -> std::io::Result<()> {
+        let mut csv = BufWriter::new(File::create(path)?);
+        writeln!(csv, "step,{}", FlockMetrics::CSV_COLUMNS)?;
+        self.csv = Some(csv);
+        Ok(())
+    }
+
+    // Records measuring the boids `frame`'s step left in `boids_buffers[(frame + 1) % 2]`.
+    pub fn record(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: usize,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        let mut compute_pass = encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("Analytics Pass"),
+                timestamp_writes,
+            }
+        );
+        compute_pass.set_bind_group(0, &self.bind_groups[(frame + 1) % 2], &[]);
+        let workgroups = self.capacity.div_ceil(64);
+        compute_pass.set_pipeline(&self.neighbours_pipeline);
+        compute_pass.dispatch_workgroups(workgroups, 1, 1);
+        compute_pass.set_pipeline(&self.clusters_pipeline);
+        compute_pass.dispatch_workgroups(workgroups, 1, 1);
+        compute_pass.set_pipeline(&self.moments_pipeline);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+        compute_pass.set_pipeline(&self.spread_pipeline);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+        drop(compute_pass);
+
+        self.pending.push(frame);
+        if self.pending.len() == BATCH {
+            self.flush(device, encoder);
+        }
+    }
+
+    // Records reading back the metrics recorded so far, without waiting for the batch to
+    // fill up. When they're streamed to a CSV this waits for the last batch to arrive, if
+    // it's still on its way, so the file has every step; otherwise the batch is dropped.
+    pub fn flush(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        if self.pending.is_empty() { return; }
+        if self.readback.busy() && self.csv.is_some() {
+            device.poll(wgpu::Maintain::Wait);
+            self.read();
+        }
+        if self.readback.busy() {
+            log::warn!("dropping the flock metrics for {} steps, the last ones haven't been read yet", self.pending.len());
+        } else {
+            self.readback.copy(encoder, &self.metrics_buffer);
+            self.copied = std::mem::take(&mut self.pending);
+        }
+        self.pending.clear();
+        encoder.clear_buffer(&self.slot_buffer, 0, None);
+    }
+
+    // Reads back whatever's been recorded but not read yet, waiting for it, so the CSV
+    // ends with the last step.
+    pub fn finish(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Analytics Finish Encoder"),
+            }
+        );
+        self.flush(device, &mut encoder);
+        queue.submit(std::iter::once(encoder.finish()));
+        self.map();
+        device.poll(wgpu::Maintain::Wait);
+        self.read();
+    }
+
+    // Call once the commands from `record` or `flush` have been submitted.
+    pub fn map(&mut self) {
+        self.readback.map();
+    }
+
+    // Takes in the last batch of metrics, if it's arrived.
+    pub fn read(&mut self) {
+        let Some(metrics) = self.readback.read::<FlockMetrics>() else { return };
+        for (frame, metrics) in self.copied.drain(..).zip(metrics) {
+            if let Some(csv) = &mut self.csv {
+                if let Err(err) = writeln!(csv, "{frame},{}", metrics.csv_fields()) {
+                    log::error!("stopped writing flock metrics: {err}");
+                    self.csv = None;
+                }
+            }
+            if self.history.len() == HISTORY {
+                self.history.pop_front();
+            }
+            self.history.push_back((frame, metrics));
+        }
+        if let Some(csv) = &mut self.csv {
+            if let Err(err) = csv.flush() {
+                log::error!("stopped writing flock metrics: {err}");
+                self.csv = None;
+            }
+        }
+    }
+
+    // The metrics read back so far, oldest first, with the frame they're for.
+    pub fn history(&self) -> &VecDeque<(usize, FlockMetrics)> {
+        &self.history
+    }
+}
@@ -0,0 +1,204 @@
+struct Boid {
+    pos: vec2<f32>,
+    vel: vec2<f32>,
+}
+
+struct SimParams {
+    flock_radius: f32,
+    avoid_radius: f32,
+    wall_radius: f32,
+    separation_weight: f32,
+    alignment_weight: f32,
+    cohesion_weight: f32,
+    wall_weight: f32,
+    species_count: u32,
+    dt: f32,
+    integrator: u32,
+    seed: u32,
+    frame: u32,
+    wander_weight: f32,
+    flow_weight: f32,
+}
+
+struct FlockMetrics {
+    centroid: vec2<f32>,
+    polarization: f32,
+    milling: f32,
+    nearest_neighbour: f32,
+    radius_of_gyration: f32,
+    clusters: u32,
+    population: u32,
+}
+
+// One thread's share of the sums in `cs_moments`.
+struct Sums {
+    pos: vec2<f32>,
+    heading: vec2<f32>,
+    nearest: f32,
+    nearest_count: f32,
+    roots: f32,
+}
+
+const WORKGROUP_SIZE = 256u;
+// marks a boid with nothing else alive to be near
+const NO_NEIGHBOUR = -1.0;
+
+@group(0) @binding(0) var<storage, read> boids: array<Boid>;
+@group(0) @binding(1) var<storage, read> population: u32;
+@group(0) @binding(2) var<uniform> params: SimParams;
+@group(0) @binding(3) var<storage, read_write> nearest: array<f32>;
+// union-find forest over the boids, where every root is the lowest index in its cluster
+@group(0) @binding(4) var<storage, read_write> parents: array<atomic<u32>>;
+@group(0) @binding(5) var<storage, read_write> metrics: array<FlockMetrics>;
+// where the next step's metrics go in `metrics`, cleared whenever they're read back
+@group(0) @binding(6) var<storage, read_write> slot: u32;
+
+var<workgroup> sums: array<Sums, WORKGROUP_SIZE>;
+var<workgroup> spread: array<vec2<f32>, WORKGROUP_SIZE>;
+
+// Finds each boid's nearest neighbour, and puts every boid in a cluster of its own.
+@compute
+@workgroup_size(64)
+fn cs_neighbours(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let total = population;
+    let idx = global_invocation_id.x;
+    if(idx >= total) { return; }
+
+    let pos = boids[idx].pos;
+    var best = 3.4e38;
+    for(var i = u32(0); i < total; i++) {
+        if(i == idx) { continue; }
+        let d_pos = boids[i].pos - pos;
+        best = min(best, dot(d_pos, d_pos));
+    }
+    nearest[idx] = select(sqrt(best), NO_NEIGHBOUR, total < 2u);
+    atomicStore(&parents[idx], idx);
+}
+
+// Joins the clusters of every pair of boids within the flock radius of each other.
+@compute
+@workgroup_size(64)
+fn cs_clusters(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let total = population;
+    let idx = global_invocation_id.x;
+    if(idx >= total) { return; }
+
+    let pos = boids[idx].pos;
+    let radius = params.flock_radius;
+    for(var i = idx + 1u; i < total; i++) {
+        let d_pos = boids[i].pos - pos;
+        if(dot(d_pos, d_pos) < radius * radius) {
+            join(idx, i);
+        }
+    }
+}
+
+fn find(idx: u32) -> u32 {
+    var root = idx;
+    loop {
+        let parent = atomicLoad(&parents[root]);
+        if(parent == root) { return root; }
+        root = parent;
+    }
+    return root;
+}
+
+// Hangs the higher of the two roots under the lower. Parents only ever move down, so
+// there are no cycles however the threads interleave. It's an `atomicMin` rather than a
+// compare and swap, since the GL backend can't translate those.
+fn join(a: u32, b: u32) {
+    var root_a = find(a);
+    var root_b = find(b);
+    loop {
+        if(root_a == root_b) { return; }
+        let low = min(root_a, root_b);
+        let high = max(root_a, root_b);
+        let old = atomicMin(&parents[high], low);
+        if(old == high) { return; }
+        // `high` had already been hung under `old`, and may now be under `low` instead,
+        // so those two still need joining
+        root_a = find(old);
+        root_b = find(low);
+    }
+}
+
+// Averages over the whole flock. It's a single workgroup, so every thread sums a strided
+// share of the boids and the shares are added up in a tree.
+@compute
+@workgroup_size(WORKGROUP_SIZE)
+fn cs_moments(@builtin(local_invocation_index) thread: u32) {
+    let total = population;
+
+    var own = Sums(vec2<f32>(0, 0), vec2<f32>(0, 0), 0.0, 0.0, 0.0);
+    for(var i = thread; i < total; i += WORKGROUP_SIZE) {
+        let boid = boids[i];
+        own.pos += boid.pos;
+        let speed = length(boid.vel);
+        if(speed > 0) { own.heading += boid.vel / speed; }
+        if(nearest[i] != NO_NEIGHBOUR) {
+            own.nearest += nearest[i];
+            own.nearest_count += 1.0;
+        }
+        if(atomicLoad(&parents[i]) == i) { own.roots += 1.0; }
+    }
+    sums[thread] = own;
+
+    for(var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        workgroupBarrier();
+        if(thread < stride) {
+            let other = sums[thread + stride];
+            sums[thread].pos += other.pos;
+            sums[thread].heading += other.heading;
+            sums[thread].nearest += other.nearest;
+            sums[thread].nearest_count += other.nearest_count;
+            sums[thread].roots += other.roots;
+        }
+    }
+
+    if(thread != 0u) { return; }
+    let n = f32(max(total, 1u));
+    let flock = sums[0];
+    let out = min(slot, arrayLength(&metrics) - 1u);
+    metrics[out].centroid = flock.pos / n;
+    metrics[out].polarization = length(flock.heading) / n;
+    metrics[out].nearest_neighbour = flock.nearest / max(flock.nearest_count, 1.0);
+    metrics[out].clusters = u32(flock.roots);
+    metrics[out].population = total;
+}
+
+// The averages that are taken about the centroid `cs_moments` found, after which the
+// step's metrics are complete.
+@compute
+@workgroup_size(WORKGROUP_SIZE)
+fn cs_spread(@builtin(local_invocation_index) thread: u32) {
+    let total = population;
+    let out = min(slot, arrayLength(&metrics) - 1u);
+    let centroid = metrics[out].centroid;
+
+    // the turning of each boid about the centroid, and its squared distance from it
+    var own = vec2<f32>(0, 0);
+    for(var i = thread; i < total; i += WORKGROUP_SIZE) {
+        let boid = boids[i];
+        let offset = boid.pos - centroid;
+        let dst = length(offset);
+        let speed = length(boid.vel);
+        if(dst > 0 && speed > 0) {
+            own.x += (offset.x * boid.vel.y - offset.y * boid.vel.x) / (dst * speed);
+        }
+        own.y += dst * dst;
+    }
+    spread[thread] = own;
+
+    for(var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        workgroupBarrier();
+        if(thread < stride) {
+            spread[thread] += spread[thread + stride];
+        }
+    }
+
+    if(thread != 0u) { return; }
+    let n = f32(max(total, 1u));
+    metrics[out].milling = abs(spread[0].x) / n;
+    metrics[out].radius_of_gyration = sqrt(spread[0].y / n);
+    slot = out + 1u;
+}
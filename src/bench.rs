@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use rand::prelude::*;
+
+use crate::options::Options;
+use crate::scenario::Scenario;
+use crate::simulation::{self, Simulation, NeighbourSearch};
+use crate::spawner::Spawner;
+use crate::readback::Readback;
+
+const COUNTS: &[usize] = &[1000, 2000, 5000, 10000, 20000];
+const STEPS: u32 = 100;
+// Steps run before timing starts, so pipelines and caches are warm.
+const WARMUP_STEPS: u32 = 10;
+
+// One boid count and neighbour search, timed over a batch of steps.
+#[derive(Debug, serde::Serialize)]
+struct BenchResult {
+    search: &'static str,
+    boids: usize,
+    steps: u32,
+    // the time between the first step starting and the last ending on the GPU, when the
+    // adapter supports timestamp queries
+    gpu_ms_per_step: Option<f64>,
+    // the time from submitting the steps to the queue saying they're done
+    wall_ms_per_step: f64,
+}
+
+// Times `cs_main` without a window, for every neighbour search at each boid count, and
+// writes the results to `path`, as JSON if it ends in `.json` and CSV otherwise. Every
+// step of a batch is recorded into one submission, so they all see the same frame
+// number, which only matters for wander.
+pub async fn run(options: &Options, scenario: &Scenario, path: &str) -> anyhow::Result<()> {
+    let (device, queue) = simulation::headless_device(wgpu::Features::TIMESTAMP_QUERY).await?;
+    let timestamps = device.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+    if !timestamps {
+        log::warn!("GPU timestamps aren't supported, so only wall clock times are reported");
+    }
+
+    let seed = options.seed.unwrap_or(0);
+    let spawner = options.spawner.unwrap_or(Spawner::Square);
+    let steps = options.bench_steps.unwrap_or(STEPS);
+    let interactions = scenario.interactions();
+    let params = crate::sim_params(options, scenario, &interactions, seed);
+
+    let mut results = Vec::new();
+    for &count in options.bench_counts.as_deref().unwrap_or(COUNTS) {
+        for search in NeighbourSearch::ALL {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let (boids, traits) = spawner.spawn(&mut rng, count);
+            let mut simulation = Simulation::new(&device, &queue, scenario, params, &interactions, &boids, &traits, seed, search)?;
+
+            run_steps(&device, &queue, &mut simulation, 0, WARMUP_STEPS, params.dt, None);
+
+            let query_set = timestamps.then(|| device.create_query_set(
+                &wgpu::QuerySetDescriptor {
+                    label: Some("Bench Query Set"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: 2,
+                }
+            ));
+            let (wall, gpu_ns) = run_steps(&device, &queue, &mut simulation, WARMUP_STEPS as usize, steps, params.dt, query_set.as_ref());
+
+            let result = BenchResult {
+                search: search.name(),
+                boids: count,
+                steps,
+                gpu_ms_per_step: gpu_ns.map(|ns| ns / 1e6 / steps as f64),
+                wall_ms_per_step: wall.as_secs_f64() * 1000.0 / steps as f64,
+            };
+            log::info!("{result:?}");
+            results.push(result);
+        }
+    }
+
+    let report = match path.ends_with(".json") {
+        true => serde_json::to_string_pretty(&results)?,
+        false => {
+            let mut csv = "search,boids,steps,gpu_ms_per_step,wall_ms_per_step\n".to_string();
+            for r in &results {
+                let gpu = r.gpu_ms_per_step.map_or(String::new(), |ms| ms.to_string());
+                csv += &format!("{},{},{},{gpu},{}\n", r.search, r.boids, r.steps, r.wall_ms_per_step);
+            }
+            csv
+        }
+    };
+    std::fs::write(path, report)?;
+    log::info!("wrote benchmark report to {path:?}");
+    Ok(())
+}
+
+// Submits `steps` steps from `first_frame` on and waits for them to finish, returning how
+// long that took and, with a query set, the nanoseconds between the first step starting
+// and the last ending.
+fn run_steps(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    simulation: &mut Simulation,
+    first_frame: usize,
+    steps: u32,
+    dt: f32,
+    query_set: Option<&wgpu::QuerySet>,
+) -> (Duration, Option<f64>) {
+    let mut encoder = device.create_command_encoder(
+        &wgpu::CommandEncoderDescriptor {
+            label: Some("Bench Encoder"),
+        }
+    );
+    for step in 0..steps {
+        let timestamp_writes = query_set.map(|query_set| wgpu::ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: (step == 0).then_some(0),
+            end_of_pass_write_index: (step == steps - 1).then_some(1),
+        });
+        simulation.step(queue, &mut encoder, first_frame + step as usize, dt, timestamp_writes);
+    }
+
+    let size = 2 * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+    let mut readback = query_set.map(|query_set| {
+        let resolve_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Bench Resolve Buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }
+        );
+        encoder.resolve_query_set(query_set, 0..2, &resolve_buffer, 0);
+        let mut readback = Readback::new(device, "Bench Readback Buffer", size);
+        readback.copy(&mut encoder, &resolve_buffer);
+        readback
+    });
+
+    let start = Instant::now();
+    let submission = queue.submit(std::iter::once(encoder.finish()));
+    device.poll(wgpu::Maintain::wait_for(submission));
+    let wall = start.elapsed();
+
+    let gpu_ns = readback.as_mut().and_then(|readback| {
+        readback.map();
+        device.poll(wgpu::Maintain::Wait);
+        let timestamps = readback.read::<u64>()?;
+        let ticks = timestamps[1].saturating_sub(timestamps[0]);
+        Some(ticks as f64 * queue.get_timestamp_period() as f64)
+    });
+    (wall, gpu_ns)
+}
@@ -0,0 +1,15 @@
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+// Colour drawn over the whole target, blended by its alpha.
+@group(0) @binding(2) var<uniform> fade_color: vec4<f32>;
+
+@fragment
+fn fs_blit(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source, source_sampler, in.uv);
+}
+
+@fragment
+fn fs_fade(in: VertexOutput) -> @location(0) vec4<f32> {
+    return fade_color;
+}
@@ -16,3 +16,162 @@ impl Default for Boid {
         Self { pos: [0., 0.], vel: [0., 0.] }
     }
 }
+
+// Per-boid attributes that don't change during the simulation. These live in their own
+// buffer next to the ping-ponged `Boid` buffers so the compute pass only has to write
+// the state that actually moves.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BoidTraits {
+    color: [f32; 4],
+    species: u32,
+    size: f32,
+    max_speed: f32,
+    separation_mul: f32,
+    alignment_mul: f32,
+    cohesion_mul: f32,
+    _padding: [f32; 2],
+}
+
+impl BoidTraits {
+    pub const fn new(species: u32, color: [f32; 4]) -> Self {
+        Self {
+            color,
+            species,
+            size: 1.0,
+            max_speed: 1.0,
+            separation_mul: 1.0,
+            alignment_mul: 1.0,
+            cohesion_mul: 1.0,
+            _padding: [0.0; 2],
+        }
+    }
+
+    pub const fn with_size(self, size: f32) -> Self {
+        Self { size, ..self }
+    }
+
+    pub const fn with_max_speed(self, max_speed: f32) -> Self {
+        Self { max_speed, ..self }
+    }
+
+    pub const fn species(&self) -> u32 {
+        self.species
+    }
+
+    pub const fn with_weights(self, separation_mul: f32, alignment_mul: f32, cohesion_mul: f32) -> Self {
+        Self { separation_mul, alignment_mul, cohesion_mul, ..self }
+    }
+}
+
+impl Default for BoidTraits {
+    fn default() -> Self {
+        Self::new(0, [0.11658, 0.05112, 0.38891, 1.0])
+    }
+}
+
+// The populations spawned by default. A boid picks one of these at random, so the
+// relative frequency of a species is how many times it's listed.
+pub const SPECIES: &[BoidTraits] = &[
+    BoidTraits::new(0, [0.11658, 0.05112, 0.38891, 1.0]),
+    BoidTraits::new(0, [0.11658, 0.05112, 0.38891, 1.0]),
+    BoidTraits::new(1, [0.02217, 0.25818, 0.23455, 1.0])
+        .with_size(1.4)
+        .with_max_speed(1.2)
+        .with_weights(1.0, 1.5, 0.6),
+];
+
+// Written by `cs_main` each step for the renderer: how many boids are within the flock
+// radius, and how far this one is from the centre of its flock.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BoidStats {
+    neighbours: f32,
+    centre_dst: f32,
+}
+
+// How a boid of one species reacts to a neighbour of another. Positive cohesion pulls
+// the boid towards the neighbour, negative cohesion pushes it away. Whatever's left out
+// of a scenario's table is zero, so the neighbour is ignored.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Interaction {
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+}
+
+impl Interaction {
+    pub const fn new(separation: f32, alignment: f32, cohesion: f32) -> Self {
+        Self { separation, alignment, cohesion }
+    }
+}
+
+// Row-major `species_count x species_count` matrix, indexed as `[self * n + other]`.
+pub struct InteractionMatrix {
+    species_count: usize,
+    entries: Vec<Interaction>,
+}
+
+impl InteractionMatrix {
+    // Every species keeps its distance from every other, but only flocks with its own.
+    pub fn segregated(species_count: usize) -> Self {
+        let mut entries = Vec::with_capacity(species_count * species_count);
+        for i in 0..species_count {
+            for j in 0..species_count {
+                entries.push(match i == j {
+                    true  => Interaction::new(1.0, 1.0, 1.0),
+                    false => Interaction::new(1.0, 0.0, 0.0),
+                });
+            }
+        }
+        Self { species_count, entries }
+    }
+
+    // From a table with a row per species, of how it reacts to each species in turn.
+    pub fn from_rows(rows: &[Vec<Interaction>]) -> anyhow::Result<Self> {
+        let species_count = species_count();
+        anyhow::ensure!(
+            rows.len() == species_count,
+            "the interaction table needs a row for each of the {species_count} species, not {}",
+            rows.len(),
+        );
+        for (i, row) in rows.iter().enumerate() {
+            anyhow::ensure!(
+                row.len() == species_count,
+                "row {i} of the interaction table needs {species_count} entries, not {}",
+                row.len(),
+            );
+        }
+        Ok(Self { species_count, entries: rows.concat() })
+    }
+
+    pub fn species_count(&self) -> usize {
+        self.species_count
+    }
+
+    pub fn entries(&self) -> &[Interaction] {
+        &self.entries
+    }
+}
+
+pub fn species_count() -> usize {
+    SPECIES.iter().map(|t| t.species() as usize + 1).max().unwrap_or(1)
+}
+
+// State for the 3D mode. WGSL aligns `vec3<f32>` to 16 bytes, hence the padding.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Boid3D {
+    pos: [f32; 3],
+    _pos_padding: f32,
+    vel: [f32; 3],
+    _vel_padding: f32,
+}
+
+impl Boid3D {
+    pub fn new(pos: [f32; 3], vel: [f32; 3]) -> Self {
+        Self { pos, _pos_padding: 0.0, vel, _vel_padding: 0.0 }
+    }
+}
@@ -58,6 +58,27 @@ impl Camera {
     }
 
 
+    // Inverse of `into_matrix` for a point in window coordinates.
+    pub fn screen_to_world(
+        &self,
+        position: winit::dpi::PhysicalPosition<f64>,
+        viewport_size: winit::dpi::PhysicalSize<u32>,
+    ) -> [f32; 2] {
+        let sf = self.scale_factor;
+        let [sx, sy] = self.scale;
+        let [px, py] = self.position;
+        let clip_x = 2.0 * position.x as f32 / viewport_size.width as f32 - 1.0;
+        let clip_y = 1.0 - 2.0 * position.y as f32 / viewport_size.height as f32;
+        [(clip_x + px * sf) / (sf * sx), (clip_y + py * sf) / (sf * sy)]
+    }
+
+    // How many pixels across one world unit is. `scale` maps the viewport's width to 5
+    // units of clip space, which is 2 units wide.
+    pub fn pixels_per_unit(&self) -> f32 {
+        self.scale_factor * 5.0 / 2.0
+    }
+
+    #[allow(clippy::wrong_self_convention)]
     pub fn into_matrix(&self) -> CameraUniform {
         let sf = self.scale_factor;
         let [sx, sy] = self.scale;
@@ -0,0 +1,111 @@
+// How `fs_main` picks a boid's colour. Everything but `Species` maps a per-boid value
+// through `range` onto a colour ramp.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    Species = 0,
+    Heading = 1,
+    Speed = 2,
+    Density = 3,
+    FlockCentre = 4,
+}
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorRamp {
+    Viridis = 0,
+    Magma = 1,
+    Coolwarm = 2,
+    Grayscale = 3,
+    Rainbow = 4,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColoringUniform {
+    pub mode: u32,
+    pub ramp: u32,
+    pub range: [f32; 2],
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 5] = [
+        ColorMode::Species,
+        ColorMode::Heading,
+        ColorMode::Speed,
+        ColorMode::Density,
+        ColorMode::FlockCentre,
+    ];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Species => "species",
+            ColorMode::Heading => "heading",
+            ColorMode::Speed => "speed",
+            ColorMode::Density => "density",
+            ColorMode::FlockCentre => "flock-centre",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    // The range of values that spans the whole ramp, and the ramp that suits the mode.
+    pub fn defaults(self) -> ([f32; 2], ColorRamp) {
+        match self {
+            ColorMode::Species => ([0.0, 1.0], ColorRamp::Viridis),
+            ColorMode::Heading => ([0.0, 1.0], ColorRamp::Rainbow),
+            ColorMode::Speed => ([0.0, 1.5], ColorRamp::Viridis),
+            ColorMode::Density => ([0.0, 12.0], ColorRamp::Magma),
+            ColorMode::FlockCentre => ([0.0, 4.0], ColorRamp::Coolwarm),
+        }
+    }
+}
+
+impl ColorRamp {
+    pub const ALL: [ColorRamp; 5] = [
+        ColorRamp::Viridis,
+        ColorRamp::Magma,
+        ColorRamp::Coolwarm,
+        ColorRamp::Grayscale,
+        ColorRamp::Rainbow,
+    ];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorRamp::Viridis => "viridis",
+            ColorRamp::Magma => "magma",
+            ColorRamp::Coolwarm => "coolwarm",
+            ColorRamp::Grayscale => "grayscale",
+            ColorRamp::Rainbow => "rainbow",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.name() == name)
+    }
+}
+
+impl ColoringUniform {
+    pub fn new(mode: ColorMode, ramp: Option<ColorRamp>) -> Self {
+        let (range, default_ramp) = mode.defaults();
+        Self { mode: mode as u32, ramp: ramp.unwrap_or(default_ramp) as u32, range }
+    }
+
+    pub fn mode(&self) -> ColorMode {
+        ColorMode::ALL[self.mode as usize]
+    }
+
+    pub fn ramp(&self) -> ColorRamp {
+        ColorRamp::ALL[self.ramp as usize]
+    }
+}
@@ -1,76 +1,342 @@
+// The boids kernel, shared by the 2D and 3D modes. It's written against `vecN`, which
+// `compute2d.wgsl` or `compute3d.wgsl` defines along with whatever else depends on the
+// number of dimensions. Goals and the flow field are 2D, so in 3D they act in the plane
+// z = 0.
+
 struct Boid {
-    pos: vec2<f32>,
-    vel: vec2<f32>,
+    pos: vecN,
+    vel: vecN,
+}
+
+struct BoidTraits {
+    color: vec4<f32>,
+    species: u32,
+    size: f32,
+    max_speed: f32,
+    separation_mul: f32,
+    alignment_mul: f32,
+    cohesion_mul: f32,
+}
+
+// What the renderer needs to know about a boid's neighbourhood, for colouring.
+struct BoidStats {
+    neighbours: f32,
+    centre_dst: f32,
+}
+
+struct Steering {
+    acceleration: vecN,
+    stats: BoidStats,
+}
+
+// Running sums over a boid's neighbours.
+struct Flock {
+    separation: vecN,
+    alignment: vecN,
+    cohesion: vecN,
+    centre: vecN,
+    n_flock: i32,
+    n_neighbours: i32,
 }
 
+struct Interaction {
+    separation: f32,
+    alignment: f32,
+    cohesion: f32,
+}
+
+struct SimParams {
+    flock_radius: f32,
+    avoid_radius: f32,
+    wall_radius: f32,
+    separation_weight: f32,
+    alignment_weight: f32,
+    cohesion_weight: f32,
+    wall_weight: f32,
+    species_count: u32,
+    dt: f32,
+    integrator: u32,
+    seed: u32,
+    frame: u32,
+    wander_weight: f32,
+    flow_weight: f32,
+}
+
+struct Goal {
+    point: vec2<f32>,
+    kind: u32,
+    species: u32,
+    weight: f32,
+    lookahead: f32,
+    first_waypoint: u32,
+    waypoint_count: u32,
+}
+
+const TAU = 6.28318530718;
+
+// point and cursor goals both seek `Goal::point`, only paths need special handling
+const GOAL_PATH = 1u;
+const ANY_SPECIES = 0xffffffffu;
+
+const INTEGRATOR_EULER = 0u;
+const INTEGRATOR_SEMI_IMPLICIT_EULER = 1u;
+const INTEGRATOR_VELOCITY_VERLET = 2u;
+const INTEGRATOR_RK2 = 3u;
+
 @group(0) @binding(0) var<storage, read> boids_src: array<Boid>;
 @group(0) @binding(1) var<storage, read_write> boids_dst: array<Boid>;
+@group(0) @binding(2) var<storage, read> traits: array<BoidTraits>;
+@group(0) @binding(3) var<uniform> params: SimParams;
+@group(0) @binding(4) var<storage, read> interactions: array<Interaction>;
+@group(0) @binding(5) var<storage, read_write> stats: array<BoidStats>;
+
+@group(1) @binding(0) var<storage, read> goals: array<Goal>;
+@group(1) @binding(1) var<storage, read> waypoints: array<vec2<f32>>;
+@group(1) @binding(2) var flow_field: texture_2d<f32>;
+// how many boids at the start of the buffers are alive
+@group(1) @binding(3) var<storage, read> population: u32;
+
+// Whether neighbours are found by sharing tiles of boids through workgroup memory, rather
+// than every invocation reading every boid itself. Either way every boid visits every
+// other. Set when the pipeline is created, see `NeighbourSearch`.
+override SHARED_MEMORY: bool = false;
+
+const TILE_SIZE = 64u;
+
+var<workgroup> tile: array<Boid, TILE_SIZE>;
+var<workgroup> tile_species: array<u32, TILE_SIZE>;
+var<private> local_index: u32;
 
 @compute
-@workgroup_size(64)
-fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
-    let total = arrayLength(&boids_src);
-    let idx = global_invocation_id.x;
-    if(idx >= total) { return; }
+@workgroup_size(TILE_SIZE)
+fn cs_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(local_invocation_index) local_invocation_index: u32,
+) {
+    let total = population;
+    local_index = local_invocation_index;
+    // every invocation helps load each tile, so dead ones carry on as boid 0 and just
+    // don't write anything
+    let alive = global_invocation_id.x < total;
+    if(!alive && !SHARED_MEMORY) { return; }
+    let idx = select(0u, global_invocation_id.x, alive);
+
+    let instance = boids_src[idx];
+    let pos = instance.pos;
+    let vel = instance.vel;
+    let dt = params.dt;
 
-    let flock_radius = 4.0;
-    let avoid_radius = 3.0;
-    let wall_radius = 512.0;
+    let steering0 = steering(idx, pos, vel);
+    let a0 = steering0.acceleration;
+    if(alive) { stats[idx] = steering0.stats; }
 
+    // Every integrator evaluates the steering forces against the neighbours' state at
+    // the start of the step. Only this boid's own state is advanced to the intermediate
+    // points, since the other boids' intermediate states aren't known yet.
+    var new_pos: vecN;
+    var new_vel: vecN;
+    switch params.integrator {
+        case INTEGRATOR_SEMI_IMPLICIT_EULER: {
+            new_vel = cruise(idx, vel + a0 * dt);
+            new_pos = pos + new_vel * dt;
+        }
+        case INTEGRATOR_VELOCITY_VERLET: {
+            new_pos = pos + vel * dt + 0.5 * a0 * dt * dt;
+            // the forces depend on velocity too, so predict it for the second evaluation
+            let a1 = acceleration(idx, new_pos, cruise(idx, vel + a0 * dt));
+            new_vel = cruise(idx, vel + 0.5 * (a0 + a1) * dt);
+        }
+        case INTEGRATOR_RK2: {
+            let mid_pos = pos + vel * (0.5 * dt);
+            let mid_vel = cruise(idx, vel + a0 * (0.5 * dt));
+            let a1 = acceleration(idx, mid_pos, mid_vel);
+            new_pos = pos + mid_vel * dt;
+            new_vel = cruise(idx, vel + a1 * dt);
+        }
+        default: {
+            // forward Euler: the position moves with the velocity from before the step
+            new_pos = pos + vel * dt;
+            new_vel = cruise(idx, vel + a0 * dt);
+        }
+    }
 
-    let separation_weight = 0.55;
-    let alignment_weight  = 0.15;
-    let cohesion_weight   = 0.05;
+    if(alive) { boids_dst[idx] = Boid(new_pos, new_vel); }
+}
 
-    var separation_force = vec2<f32>(0, 0);
-    var alignment_force  = vec2<f32>(0, 0);
-    var center_flock     = vec2<f32>(0, 0);
+fn acceleration(idx: u32, pos: vecN, vel: vecN) -> vecN {
+    return steering(idx, pos, vel).acceleration;
+}
 
+// Steering acceleration, per unit time, for boid `idx` if it were at `pos` moving at `vel`.
+fn steering(idx: u32, pos: vecN, vel: vecN) -> Steering {
+    let total = population;
 
-    let wall_weight = 3.0;
-    var wall_force  = vec2<f32>(0, 0);
+    let wall_radius = params.wall_radius;
 
-    var n_flock = 0;
-    let instance = boids_src[idx];
 
-    let dst_from_wall = wall_radius - length(instance.pos);
-    wall_force = (-instance.pos) * smoothing_kernel(2.0, dst_from_wall);
-    
+    let separation_weight = params.separation_weight;
+    let alignment_weight  = params.alignment_weight;
+    let cohesion_weight   = params.cohesion_weight;
 
-    for(var i = u32(0); i < total; i++) {
-        let other = boids_src[i];
+    let wall_weight = params.wall_weight;
+    var wall_force  = vecN();
 
-        let d_pos = other.pos - instance.pos;
-        let dt = dot(d_pos, d_pos);
-        if(dt < flock_radius * flock_radius) {
-            n_flock += 1;
-            if(dt > 0 && dt < avoid_radius * avoid_radius) { separation_force -= d_pos / (dt + 1); }
+    let instance_traits = traits[idx];
+    let row = instance_traits.species * params.species_count;
 
-            let d_vel = other.vel - instance.vel;
-            let dt_vel = length(d_vel);
-            if(dt_vel > 0) { alignment_force += d_vel; }
+    let dst_from_wall = wall_radius - length(pos);
+    wall_force = (-pos) * smoothing_kernel(2.0, dst_from_wall);
+    
 
-            center_flock += other.pos;
+    var flock = Flock(vecN(), vecN(), vecN(), vecN(), 0, 0);
+    if(SHARED_MEMORY) {
+        for(var base = u32(0); base < total; base += TILE_SIZE) {
+            workgroupBarrier();
+            let i = base + local_index;
+            if(i < total) {
+                tile[local_index] = boids_src[i];
+                tile_species[local_index] = traits[i].species;
+            }
+            workgroupBarrier();
+            let count = min(TILE_SIZE, total - base);
+            for(var k = u32(0); k < count; k++) {
+                add_neighbour(&flock, row, pos, vel, tile[k], tile_species[k]);
+            }
+        }
+    } else {
+        for(var i = u32(0); i < total; i++) {
+            add_neighbour(&flock, row, pos, vel, boids_src[i], traits[i].species);
         }
+    }
+
+    let wander_force = wander(idx) * params.wander_weight;
+    let seek = seek_force(idx, pos, vel);
+    let flow_force = planar(flow(flat(pos))) * params.flow_weight;
+    // everything that doesn't depend on the flock
+    let steering_force = wander_force + seek + flow_force;
+
+    // a boid keeps its distance from other species and the wall even when it has no
+    // flock of its own to align with and steer towards
+    var acceleration = steering_force
+         + flock.separation * separation_weight * instance_traits.separation_mul
+         + wall_force       * wall_weight;
+    if(flock.n_flock == 0) { return Steering(acceleration, BoidStats(f32(flock.n_neighbours), 0.0)); }
+
+    let n_flock = f32(flock.n_flock);
+    let centre_dst = length(flock.centre / n_flock);
+
+    acceleration += flock.alignment / n_flock * alignment_weight * instance_traits.alignment_mul
+                  + flock.cohesion  / n_flock * cohesion_weight  * instance_traits.cohesion_mul;
+    return Steering(acceleration, BoidStats(f32(flock.n_neighbours), centre_dst));
+}
+
+// Adds `other` to the forces on a boid at `pos` moving at `vel`, if it's close enough.
+fn add_neighbour(flock: ptr<function, Flock>, row: u32, pos: vecN, vel: vecN, other: Boid, species: u32) {
+    let flock_radius = params.flock_radius;
+    let avoid_radius = params.avoid_radius;
+
+    let d_pos = other.pos - pos;
+    let dt = dot(d_pos, d_pos);
+    if(dt >= flock_radius * flock_radius) { return; }
+
+    if(dt > 0) { (*flock).n_neighbours += 1; }
+    let coef = interactions[row + species];
+    if(dt > 0 && dt < avoid_radius * avoid_radius) { (*flock).separation -= coef.separation * d_pos / (dt + 1); }
+
+    // species we don't flock with don't count towards the flock average
+    if(coef.alignment == 0 && coef.cohesion == 0) { return; }
+    (*flock).n_flock += 1;
+
+    let d_vel = other.vel - vel;
+    let dt_vel = length(d_vel);
+    if(dt_vel > 0) { (*flock).alignment += coef.alignment * d_vel; }
+
+    (*flock).cohesion += coef.cohesion * d_pos;
+    (*flock).centre += d_pos;
+}
+
+// Boids always fly at their species' top speed, only their heading changes.
+fn cruise(idx: u32, vel: vecN) -> vecN {
+    let speed = length(vel);
+    if(speed == 0) { return vel; }
+    return vel * (traits[idx].max_speed / speed);
+}
 
+// Reynolds style seek towards every goal this boid's species follows.
+fn seek_force(idx: u32, pos: vecN, vel: vecN) -> vecN {
+    let instance_traits = traits[idx];
+    var force = vecN();
+    for(var g = u32(0); g < arrayLength(&goals); g++) {
+        let goal = goals[g];
+        if(goal.weight == 0) { continue; }
+        if(goal.species != ANY_SPECIES && goal.species != instance_traits.species) { continue; }
+
+        var goal_pos = planar(goal.point);
+        if(goal.kind == GOAL_PATH) { goal_pos = planar(path_target(goal, flat(pos))); }
+
+        let to_goal = goal_pos - pos;
+        let dst = length(to_goal);
+        if(dst == 0) { continue; }
+
+        let desired = to_goal * (instance_traits.max_speed / dst);
+        force += (desired - vel) * goal.weight;
     }
-    
-    let new_pos = instance.pos + instance.vel * 0.20;
-    var new_vel =  instance.vel;
-    if(n_flock > 0) {
-        alignment_force /= f32(n_flock);
-        let cohesion_force = (center_flock / f32(n_flock)) - instance.pos;
-        // let cohesion_force = vec2<f32>(0, 0);
-
-        let acceleration = separation_force * separation_weight
-                         + alignment_force  * alignment_weight
-                         + cohesion_force   * cohesion_weight
-                         + wall_force       * wall_weight;
-
-        new_vel += acceleration;
-        new_vel /= length(new_vel);
+    return force;
+}
+
+// The point `lookahead` units past the closest point on the goal's closed path.
+fn path_target(goal: Goal, pos: vec2<f32>) -> vec2<f32> {
+    var best_dst = 3.4e38;
+    var best_point = pos;
+    var best_dir = vec2<f32>(0, 0);
+    for(var k = u32(0); k < goal.waypoint_count; k++) {
+        let a = waypoints[goal.first_waypoint + k];
+        let b = waypoints[goal.first_waypoint + (k + 1) % goal.waypoint_count];
+        let ab = b - a;
+        let len2 = max(dot(ab, ab), 1e-6);
+        let t = clamp(dot(pos - a, ab) / len2, 0.0, 1.0);
+        let p = a + ab * t;
+        let d = dot(pos - p, pos - p);
+        if(d < best_dst) {
+            best_dst = d;
+            best_point = p;
+            best_dir = ab / sqrt(len2);
+        }
     }
-    boids_dst[idx] = Boid(new_pos, new_vel);
+    return best_point + best_dir * goal.lookahead;
+}
+
+// Bilinearly filtered flow field at `pos`. The field covers the area inside the wall.
+fn flow(pos: vec2<f32>) -> vec2<f32> {
+    let size = vec2<i32>(textureDimensions(flow_field));
+    let uv = pos / (2.0 * params.wall_radius) + 0.5;
+    let texel = uv * vec2<f32>(size) - 0.5;
+    let base = floor(texel);
+    let f = texel - base;
+
+    let lo = clamp(vec2<i32>(base), vec2<i32>(0, 0), size - 1);
+    let hi = clamp(vec2<i32>(base) + 1, vec2<i32>(0, 0), size - 1);
+    let v00 = textureLoad(flow_field, vec2<i32>(lo.x, lo.y), 0).xy;
+    let v10 = textureLoad(flow_field, vec2<i32>(hi.x, lo.y), 0).xy;
+    let v01 = textureLoad(flow_field, vec2<i32>(lo.x, hi.y), 0).xy;
+    let v11 = textureLoad(flow_field, vec2<i32>(hi.x, hi.y), 0).xy;
+    return mix(mix(v00, v10, f.x), mix(v01, v11, f.x), f.y);
+}
+
+fn rng_seed(idx: u32) -> u32 {
+    return pcg_hash(idx ^ pcg_hash(params.frame ^ pcg_hash(params.seed)));
+}
+
+// PCG output permutation, used as a stateless hash.
+fn pcg_hash(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random_f32(state: ptr<function, u32>) -> f32 {
+    *state = pcg_hash(*state);
+    return f32(*state >> 8u) / 16777216.0;
 }
 
 fn smoothing_kernel(r: f32, dst: f32) -> f32 {
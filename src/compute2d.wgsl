@@ -0,0 +1,18 @@
+alias vecN = vec2<f32>;
+
+// To and from the plane goals and the flow field are in, which is all there is in 2D.
+fn planar(v: vec2<f32>) -> vecN {
+    return v;
+}
+
+fn flat(v: vecN) -> vec2<f32> {
+    return v;
+}
+
+// A random unit steering direction, fixed for a given boid, frame and seed so runs
+// replay exactly.
+fn wander(idx: u32) -> vecN {
+    var state = rng_seed(idx);
+    let a = random_f32(&state) * TAU;
+    return vec2<f32>(cos(a), sin(a));
+}
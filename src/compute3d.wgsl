@@ -0,0 +1,20 @@
+alias vecN = vec3<f32>;
+
+// To and from the plane z = 0, which goals and the flow field are in.
+fn planar(v: vec2<f32>) -> vecN {
+    return vec3<f32>(v, 0.0);
+}
+
+fn flat(v: vecN) -> vec2<f32> {
+    return v.xy;
+}
+
+// A random unit steering direction, uniform over the sphere, fixed for a given boid,
+// frame and seed so runs replay exactly.
+fn wander(idx: u32) -> vecN {
+    var state = rng_seed(idx);
+    let z = 2.0 * random_f32(&state) - 1.0;
+    let a = random_f32(&state) * TAU;
+    let r = sqrt(1.0 - z * z);
+    return vec3<f32>(r * cos(a), r * sin(a), z);
+}
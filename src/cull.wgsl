@@ -0,0 +1,69 @@
+struct Boid {
+    pos: vec2<f32>,
+    vel: vec2<f32>,
+}
+
+struct BoidTraits {
+    color: vec4<f32>,
+    species: u32,
+    size: f32,
+    max_speed: f32,
+    separation_mul: f32,
+    alignment_mul: f32,
+    cohesion_mul: f32,
+}
+
+struct BoidStats {
+    neighbours: f32,
+    centre_dst: f32,
+}
+
+// Laid out like `wgpu::util::DrawIndirectArgs`.
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+}
+
+// Which of `draw_args` is which, matching `Culling`.
+const ARGS_VISIBLE = 0u;
+const ARGS_SPLATS = 1u;
+const ARGS_HISTORY = 2u;
+
+// How far a boid can reach from its position, in multiples of its size. Covers the
+// sprite quads and the built-in shapes.
+const REACH = 2.0;
+
+@group(0) @binding(0) var<storage, read> boids: array<Boid>;
+@group(0) @binding(1) var<storage, read> traits: array<BoidTraits>;
+@group(0) @binding(2) var<storage, read> stats: array<BoidStats>;
+@group(0) @binding(3) var<uniform> camera_mat: mat3x3<f32>;
+@group(0) @binding(4) var<storage, read_write> visible_boids: array<Boid>;
+@group(0) @binding(5) var<storage, read_write> visible_traits: array<BoidTraits>;
+@group(0) @binding(6) var<storage, read_write> visible_stats: array<BoidStats>;
+@group(0) @binding(7) var<storage, read_write> draw_args: array<DrawArgs, 3>;
+@group(0) @binding(8) var<storage, read> population: u32;
+
+// Packs the live boids that can be seen into the `visible_*` buffers and counts them as
+// the instances to draw. The draws that cover every live boid get the population.
+@compute
+@workgroup_size(64)
+fn cs_cull(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let idx = global_invocation_id.x;
+    if(idx == 0u) {
+        atomicStore(&draw_args[ARGS_SPLATS].instance_count, population);
+        atomicStore(&draw_args[ARGS_HISTORY].instance_count, population);
+    }
+    if(idx >= population) { return; }
+
+    let boid = boids[idx];
+    let clip = (camera_mat * vec3<f32>(boid.pos, 1.0)).xy;
+    let reach = traits[idx].size * REACH * vec2<f32>(length(camera_mat[0].xy), length(camera_mat[1].xy));
+    if(any(abs(clip) > 1.0 + reach)) { return; }
+
+    let slot = atomicAdd(&draw_args[ARGS_VISIBLE].instance_count, 1u);
+    visible_boids[slot] = boid;
+    visible_traits[slot] = traits[idx];
+    visible_stats[slot] = stats[idx];
+}
@@ -0,0 +1,214 @@
+use wgpu::util::DeviceExt;
+
+use crate::boid::{Boid, BoidTraits, BoidStats};
+use crate::heatmap::SPLAT_VERTICES;
+use crate::trails::HISTORY_VERTICES;
+
+// Offsets into `Culling::draw_args` of the indirect draws for the visible boids, the
+// heatmap splats and the trail history, in that order.
+pub const VISIBLE_ARGS: wgpu::BufferAddress = 0;
+pub const SPLAT_ARGS: wgpu::BufferAddress = 16;
+pub const HISTORY_ARGS: wgpu::BufferAddress = 32;
+
+// Frustum culling on the GPU. Live boids that could be on screen are copied into compact
+// instance buffers, and the count of them goes straight into an indirect draw, so the
+// renderer never needs to know how many there are. The draws covering every live boid
+// get their instance counts from here too.
+pub struct Culling {
+    n_boids: u32,
+
+    pub visible_boids: wgpu::Buffer,
+    pub visible_traits: wgpu::Buffer,
+    pub visible_stats: wgpu::Buffer,
+    pub draw_args: wgpu::Buffer,
+
+    bind_groups: Vec<wgpu::BindGroup>,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Culling {
+    pub fn new(
+        device: &wgpu::Device,
+        boids_buffers: &[wgpu::Buffer],
+        traits_buffer: &wgpu::Buffer,
+        stats_buffer: &wgpu::Buffer,
+        camera_buffer: &wgpu::Buffer,
+        population_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let n_boids = (boids_buffers[0].size() / std::mem::size_of::<Boid>() as u64) as u32;
+
+        let instance_buffer = |label: &str, size: usize| device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some(label),
+                size: (n_boids as usize * size) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }
+        );
+        let visible_boids = instance_buffer("Visible Boids Buffer", std::mem::size_of::<Boid>());
+        let visible_traits = instance_buffer("Visible Traits Buffer", std::mem::size_of::<BoidTraits>());
+        let visible_stats = instance_buffer("Visible Stats Buffer", std::mem::size_of::<BoidStats>());
+
+        let draw_args = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Draw Args Buffer"),
+                contents: &[0; 3 * std::mem::size_of::<wgpu::util::DrawIndirectArgs>()],
+                usage: wgpu::BufferUsages::INDIRECT
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Cull Bind Group Layout"),
+                entries: &[
+                    storage_entry(0, true),
+                    storage_entry(1, true),
+                    storage_entry(2, true),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    storage_entry(4, false),
+                    storage_entry(5, false),
+                    storage_entry(6, false),
+                    storage_entry(7, false),
+                    storage_entry(8, true),
+                ],
+            }
+        );
+
+        let bind_groups = boids_buffers.iter().enumerate()
+            .map(|(i, boids_buffer)| device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    label: Some(format!("Cull Bind Group {}", i).as_str()),
+                    layout: &bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: boids_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: traits_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: stats_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: camera_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: visible_boids.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: visible_traits.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 6,
+                            resource: visible_stats.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 7,
+                            resource: draw_args.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 8,
+                            resource: population_buffer.as_entire_binding(),
+                        },
+                    ],
+                }
+            ))
+            .collect();
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("cull.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Cull Pipeline Layout"),
+                bind_group_layouts: &[
+                    &bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+        let pipeline = device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("Cull Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "cs_cull",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            }
+        );
+
+        Self {
+            n_boids,
+
+            visible_boids,
+            visible_traits,
+            visible_stats,
+            draw_args,
+
+            bind_groups,
+            pipeline,
+        }
+    }
+
+    // Culls the boids in `boids_buffers[buffer]` against the camera, leaving `draw_args`
+    // set up to draw `vertex_count` vertices for each visible boid.
+    pub fn cull(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        buffer: usize,
+        vertex_count: u32,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        // the instance counts are filled in by `cs_cull`
+        for (offset, vertex_count) in [
+            (VISIBLE_ARGS, vertex_count),
+            (SPLAT_ARGS, SPLAT_VERTICES),
+            (HISTORY_ARGS, HISTORY_VERTICES),
+        ] {
+            let args = wgpu::util::DrawIndirectArgs {
+                vertex_count,
+                instance_count: 0,
+                first_vertex: 0,
+                first_instance: 0,
+            };
+            queue.write_buffer(&self.draw_args, offset, args.as_bytes());
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("Cull Pass"),
+                timestamp_writes,
+            }
+        );
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_groups[buffer], &[]);
+        compute_pass.dispatch_workgroups(self.n_boids.div_ceil(64), 1, 1);
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+
+// What can go wrong setting up or running on the GPU, worded for whoever's running the
+// program rather than whoever's debugging it. `run` wraps these in `anyhow` on the way out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Surface(String),
+    NoAdapter,
+    RequestDevice(String),
+    NoSurfaceFormat,
+    DeviceLost(String),
+    ShaderValidation(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Surface(message) => write!(f, "couldn't create a surface to draw to the window: {message}"),
+            Error::NoAdapter => write!(
+                f,
+                "no suitable GPU was found; check the graphics drivers support Vulkan, Metal or DirectX 12, \
+                 or set WGPU_BACKEND to try another backend",
+            ),
+            Error::RequestDevice(message) => write!(f, "the GPU was found but couldn't be opened: {message}"),
+            Error::NoSurfaceFormat => write!(f, "the GPU can't draw to this window in any format it supports"),
+            Error::DeviceLost(message) => write!(f, "the GPU device was lost: {message}"),
+            Error::ShaderValidation(message) => write!(f, "a shader or pipeline failed validation: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,953 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::boid::{Boid, BoidTraits};
+use crate::options::Options;
+use crate::trajectory::{Frame, Replay};
+
+// What a trajectory can be exported as, named by the extension of the file it's
+// exported to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    // a file per frame with a row per boid
+    Csv,
+    // a single (frames, boids, 4) array of x, y, vx and vy
+    Npy,
+    // that array along with each frame's step and population and each boid's species
+    Npz,
+    // a row per boid per frame
+    Parquet,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [
+        ExportFormat::Csv,
+        ExportFormat::Npy,
+        ExportFormat::Npz,
+        ExportFormat::Parquet,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Npy => "npy",
+            ExportFormat::Npz => "npz",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        Self::from_name(&extension.to_ascii_lowercase())
+    }
+}
+
+// Exports the trajectory in `path` to `--export-to`, or next to it as `.npz` if that
+// isn't given.
+pub fn run(options: &Options, path: &str) -> anyhow::Result<()> {
+    let mut replay = Replay::load(path)?;
+    let output = match &options.export_to {
+        Some(output) => output.clone(),
+        None => Path::new(path).with_extension("npz").to_string_lossy().into_owned(),
+    };
+    let format = ExportFormat::from_path(&output).ok_or_else(|| {
+        let names: Vec<&str> = ExportFormat::ALL.iter().map(|f| f.name()).collect();
+        anyhow::anyhow!("can't tell what to export {output:?} as, expected a file ending in one of {names:?}")
+    })?;
+    match format {
+        ExportFormat::Csv => write_csv(&mut replay, &output)?,
+        ExportFormat::Npy => {
+            let mut npy = BufWriter::new(File::create(&output)?);
+            npy.write_all(&npy_header("f4", &boids_shape(&replay)))?;
+            write_boids(&mut replay, &mut npy)?;
+            npy.flush()?;
+        }
+        ExportFormat::Npz => write_npz(&mut replay, &output)?,
+        ExportFormat::Parquet => write_parquet(&mut replay, &output, ROW_GROUP_ROWS)?,
+    }
+    log::info!(
+        "exported {} frames of up to {} boids to {output:?}",
+        replay.frame_count(),
+        replay.max_population(),
+    );
+    Ok(())
+}
+
+fn species(frame: &Frame, initial_traits: &[BoidTraits], index: usize) -> u32 {
+    frame.traits.as_ref().map_or(&initial_traits[index], |traits| &traits[index]).species()
+}
+
+// A file per frame next to `path`, named after it with the frame's index added, so
+// `frames.csv` becomes `frames_000000.csv`, `frames_000001.csv` and so on.
+fn write_csv(replay: &mut Replay, path: &str) -> anyhow::Result<()> {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let initial_traits = replay.initial_traits().to_vec();
+    for i in 0..replay.frame_count() {
+        let frame = replay.frame(i);
+        let mut csv = BufWriter::new(File::create(path.with_file_name(format!("{stem}_{i:06}.csv")))?);
+        writeln!(csv, "step,boid,species,x,y,vx,vy")?;
+        for (b, boid) in frame.boids.iter().enumerate() {
+            let [x, y, vx, vy]: [f32; 4] = bytemuck::cast(*boid);
+            writeln!(csv, "{},{b},{},{x},{y},{vx},{vy}", frame.step, species(frame, &initial_traits, b))?;
+        }
+        csv.flush()?;
+    }
+    Ok(())
+}
+
+fn boids_shape(replay: &Replay) -> [usize; 3] {
+    [replay.frame_count(), replay.max_population(), 4]
+}
+
+// The start of a `.npy` file holding an array of `kind` elements, e.g. "f4", in the
+// byte order they're in memory. It's padded so the data after it is 64 byte aligned.
+fn npy_header(kind: &str, shape: &[usize]) -> Vec<u8> {
+    let order = if cfg!(target_endian = "little") { '<' } else { '>' };
+    let shape = match shape {
+        [len] => format!("({len},)"),
+        _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+    };
+    let mut dict = format!("{{'descr': '{order}{kind}', 'fortran_order': False, 'shape': {shape}, }}");
+    // after the magic, the version and the dict's length, and ending in a newline
+    let len = (10 + dict.len() + 1).next_multiple_of(64) - 10;
+    dict.extend(std::iter::repeat_n(' ', len - dict.len() - 1));
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend((len as u16).to_le_bytes());
+    header.extend(dict.as_bytes());
+    header
+}
+
+// The boids of every frame as they are in memory, which is x, y, vx and vy. Frames
+// with fewer boids alive than the most there ever are are padded with NaN.
+fn write_boids(replay: &mut Replay, out: &mut impl Write) -> std::io::Result<()> {
+    let padding = vec![Boid::new(f32::NAN, f32::NAN, f32::NAN, f32::NAN); replay.max_population()];
+    for i in 0..replay.frame_count() {
+        let boids = &replay.frame(i).boids;
+        out.write_all(bytemuck::cast_slice(boids))?;
+        out.write_all(bytemuck::cast_slice(&padding[boids.len()..]))?;
+    }
+    Ok(())
+}
+
+// An uncompressed `.npz` with `boids` as in a `.npy` export, along with `steps` and
+// `population` for each frame and the `species` of each boid in each frame, which is
+// -1 where `boids` is padded.
+fn write_npz(replay: &mut Replay, path: &str) -> anyhow::Result<()> {
+    let mut npz = Npz::create(path)?;
+    let [frames, max_population, _] = boids_shape(replay);
+    npz.add("boids", "f4", &boids_shape(replay), 4, |out| write_boids(replay, out))?;
+    npz.add("steps", "u4", &[frames], 4, |out| {
+        for i in 0..frames {
+            out.write_all(&replay.frame(i).step.to_ne_bytes())?;
+        }
+        Ok(())
+    })?;
+    npz.add("population", "u4", &[frames], 4, |out| {
+        for i in 0..frames {
+            out.write_all(&(replay.frame(i).boids.len() as u32).to_ne_bytes())?;
+        }
+        Ok(())
+    })?;
+    let initial_traits = replay.initial_traits().to_vec();
+    npz.add("species", "i4", &[frames, max_population], 4, |out| {
+        for i in 0..frames {
+            let frame = replay.frame(i);
+            for b in 0..max_population {
+                let species = match b < frame.boids.len() {
+                    true => species(frame, &initial_traits, b) as i32,
+                    false => -1,
+                };
+                out.write_all(&species.to_ne_bytes())?;
+            }
+        }
+        Ok(())
+    })?;
+    npz.finish()
+}
+
+// Counts what's written through it, and its checksum, for the zip entry it's in.
+struct ZipEntryWriter<'a> {
+    out: &'a mut BufWriter<File>,
+    crc: crc32fast::Hasher,
+    len: u64,
+}
+
+impl Write for ZipEntryWriter<'_> {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        let len = self.out.write(bytes)?;
+        self.crc.update(&bytes[..len]);
+        self.len += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+// A zip of `.npy` files, stored rather than compressed so the arrays are streamed
+// straight in. Each entry's checksum is only known once it's written, so it's filled
+// into the entry's header afterwards.
+struct Npz {
+    file: BufWriter<File>,
+    // the name, checksum, size and header offset of each entry
+    entries: Vec<(String, u32, u32, u32)>,
+}
+
+impl Npz {
+    // 1980-01-01, the earliest date a zip can hold
+    const DATE: u16 = 0x21;
+
+    fn create(path: &str) -> std::io::Result<Self> {
+        Ok(Self { file: BufWriter::new(File::create(path)?), entries: Vec::new() })
+    }
+
+    // Adds `name.npy` holding an array of `shape`, whose elements of `element_size`
+    // bytes are written by `write`.
+    fn add(
+        &mut self,
+        name: &str,
+        kind: &str,
+        shape: &[usize],
+        element_size: usize,
+        write: impl FnOnce(&mut ZipEntryWriter) -> std::io::Result<()>,
+    ) -> anyhow::Result<()> {
+        let name = format!("{name}.npy");
+        let header = npy_header(kind, shape);
+        let size = header.len() as u64 + (shape.iter().product::<usize>() * element_size) as u64;
+        let offset = self.file.stream_position()?;
+        anyhow::ensure!(size < u32::MAX as u64 && offset < u32::MAX as u64, "the trajectory is too big for a .npz");
+
+        self.file.write_all(&0x04034b50u32.to_le_bytes())?;
+        for value in [20, 0, 0, 0, Self::DATE] {
+            self.file.write_all(&u16::to_le_bytes(value))?;
+        }
+        // the checksum, filled in below
+        self.file.write_all(&0u32.to_le_bytes())?;
+        self.file.write_all(&(size as u32).to_le_bytes())?;
+        self.file.write_all(&(size as u32).to_le_bytes())?;
+        self.file.write_all(&(name.len() as u16).to_le_bytes())?;
+        self.file.write_all(&0u16.to_le_bytes())?;
+        self.file.write_all(name.as_bytes())?;
+
+        let mut entry = ZipEntryWriter { out: &mut self.file, crc: crc32fast::Hasher::new(), len: 0 };
+        entry.write_all(&header)?;
+        write(&mut entry)?;
+        anyhow::ensure!(entry.len == size, "wrote {} bytes of {name} rather than {size}", entry.len);
+        let crc = entry.crc.finalize();
+
+        self.file.seek(SeekFrom::Start(offset + 14))?;
+        self.file.write_all(&crc.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.entries.push((name, crc, size as u32, offset as u32));
+        Ok(())
+    }
+
+    // Writes the central directory listing the entries.
+    fn finish(mut self) -> anyhow::Result<()> {
+        let start = self.file.stream_position()?;
+        for (name, crc, size, offset) in &self.entries {
+            self.file.write_all(&0x02014b50u32.to_le_bytes())?;
+            for value in [20, 20, 0, 0, 0, Self::DATE] {
+                self.file.write_all(&u16::to_le_bytes(value))?;
+            }
+            for value in [*crc, *size, *size] {
+                self.file.write_all(&value.to_le_bytes())?;
+            }
+            for value in [name.len() as u16, 0, 0, 0, 0] {
+                self.file.write_all(&value.to_le_bytes())?;
+            }
+            self.file.write_all(&0u32.to_le_bytes())?;
+            self.file.write_all(&offset.to_le_bytes())?;
+            self.file.write_all(name.as_bytes())?;
+        }
+        let end = self.file.stream_position()?;
+        anyhow::ensure!(end < u32::MAX as u64, "the trajectory is too big for a .npz");
+
+        self.file.write_all(&0x06054b50u32.to_le_bytes())?;
+        let count = self.entries.len() as u16;
+        for value in [0, 0, count, count] {
+            self.file.write_all(&u16::to_le_bytes(value))?;
+        }
+        self.file.write_all(&((end - start) as u32).to_le_bytes())?;
+        self.file.write_all(&(start as u32).to_le_bytes())?;
+        self.file.write_all(&0u16.to_le_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+// Parquet's physical types, of the columns below.
+const INT32: i32 = 1;
+const FLOAT: i32 = 4;
+
+const PARQUET_COLUMNS: [(&str, i32); 8] = [
+    ("frame", INT32),
+    ("step", INT32),
+    ("boid", INT32),
+    ("species", INT32),
+    ("x", FLOAT),
+    ("y", FLOAT),
+    ("vx", FLOAT),
+    ("vy", FLOAT),
+];
+
+// Row groups are ended after the first frame that takes them past this many rows.
+const ROW_GROUP_ROWS: usize = 1 << 20;
+
+// A row per boid per frame, in an uncompressed, plain encoded Parquet file with a page
+// per column per row group, of at least `row_group_rows` rows but for the last.
+fn write_parquet(replay: &mut Replay, path: &str, row_group_rows: usize) -> anyhow::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(b"PAR1")?;
+    let mut offset = 4;
+
+    let initial_traits = replay.initial_traits().to_vec();
+    let mut columns = vec![Vec::new(); PARQUET_COLUMNS.len()];
+    let mut rows = 0;
+    let mut row_groups = Vec::new();
+    for i in 0..replay.frame_count() {
+        let frame = replay.frame(i);
+        for (b, boid) in frame.boids.iter().enumerate() {
+            let [x, y, vx, vy]: [f32; 4] = bytemuck::cast(*boid);
+            let ints = [i as u32, frame.step, b as u32, species(frame, &initial_traits, b)];
+            for (column, value) in columns.iter_mut().zip(ints) {
+                column.extend(value.to_le_bytes());
+            }
+            for (column, value) in columns[ints.len()..].iter_mut().zip([x, y, vx, vy]) {
+                column.extend(value.to_le_bytes());
+            }
+        }
+        rows += frame.boids.len();
+
+        if rows >= row_group_rows || i + 1 == replay.frame_count() {
+            let mut chunks = Vec::new();
+            for column in &mut columns {
+                let page = page_header(column.len(), rows);
+                file.write_all(&page)?;
+                file.write_all(column)?;
+                let size = page.len() + column.len();
+                chunks.push((offset, size));
+                offset += size;
+                column.clear();
+            }
+            row_groups.push((rows, chunks));
+            rows = 0;
+        }
+    }
+
+    let metadata = file_metadata(&row_groups);
+    file.write_all(&metadata)?;
+    file.write_all(&(metadata.len() as u32).to_le_bytes())?;
+    file.write_all(b"PAR1")?;
+    file.flush()?;
+    Ok(())
+}
+
+fn page_header(len: usize, values: usize) -> Vec<u8> {
+    let mut thrift = Thrift::default();
+    // a data page, uncompressed
+    thrift.i32(1, 0);
+    thrift.i32(2, len as i32);
+    thrift.i32(3, len as i32);
+    thrift.begin_struct(5);
+    thrift.i32(1, values as i32);
+    // plain values, and the levels there would be if the columns could be null
+    thrift.i32(2, 0);
+    thrift.i32(3, 3);
+    thrift.i32(4, 3);
+    thrift.end_struct();
+    thrift.end()
+}
+
+// The schema and where every row group's column chunks are, given each row group's
+// rows and each of its chunks' offset and size.
+fn file_metadata(row_groups: &[(usize, Vec<(usize, usize)>)]) -> Vec<u8> {
+    let mut thrift = Thrift::default();
+    thrift.i32(1, 1);
+
+    thrift.list(2, Thrift::STRUCT, PARQUET_COLUMNS.len() + 1);
+    thrift.begin_element();
+    thrift.binary(4, b"schema");
+    thrift.i32(5, PARQUET_COLUMNS.len() as i32);
+    thrift.end_struct();
+    for (name, kind) in PARQUET_COLUMNS {
+        thrift.begin_element();
+        thrift.i32(1, kind);
+        // required, as none are ever null
+        thrift.i32(3, 0);
+        thrift.binary(4, name.as_bytes());
+        thrift.end_struct();
+    }
+
+    let rows: usize = row_groups.iter().map(|(rows, _)| rows).sum();
+    thrift.i64(3, rows as i64);
+
+    thrift.list(4, Thrift::STRUCT, row_groups.len());
+    for (rows, chunks) in row_groups {
+        thrift.begin_element();
+        thrift.list(1, Thrift::STRUCT, chunks.len());
+        for (&(offset, size), (name, kind)) in chunks.iter().zip(PARQUET_COLUMNS) {
+            thrift.begin_element();
+            thrift.i64(2, offset as i64);
+            thrift.begin_struct(3);
+            thrift.i32(1, kind);
+            // plain values, and the levels' encoding the page headers give
+            thrift.list(2, Thrift::I32, 2);
+            thrift.element_i32(0);
+            thrift.element_i32(3);
+            thrift.list(3, Thrift::BINARY, 1);
+            thrift.element_binary(name.as_bytes());
+            thrift.i32(4, 0);
+            thrift.i64(5, *rows as i64);
+            thrift.i64(6, size as i64);
+            thrift.i64(7, size as i64);
+            thrift.i64(9, offset as i64);
+            thrift.end_struct();
+            thrift.end_struct();
+        }
+        let size: usize = chunks.iter().map(|(_, size)| size).sum();
+        thrift.i64(2, size as i64);
+        thrift.i64(3, *rows as i64);
+        thrift.end_struct();
+    }
+
+    thrift.binary(6, b"wgpu_boids");
+    thrift.end()
+}
+
+// Thrift's compact protocol, which Parquet's metadata is written in, for just the types
+// it needs. Field ids are written relative to the last one in the same struct.
+struct Thrift {
+    bytes: Vec<u8>,
+    last_fields: Vec<i16>,
+}
+
+impl Default for Thrift {
+    fn default() -> Self {
+        Self { bytes: Vec::new(), last_fields: vec![0] }
+    }
+}
+
+impl Thrift {
+    const I32: u8 = 5;
+    const I64: u8 = 6;
+    const BINARY: u8 = 8;
+    const LIST: u8 = 9;
+    const STRUCT: u8 = 12;
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last_fields.last_mut().unwrap();
+        match id - *last {
+            delta @ 1..=15 => self.bytes.push((delta as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(kind);
+                self.zigzag(id as i64);
+            }
+        }
+        *self.last_fields.last_mut().unwrap() = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, Self::I32);
+        self.zigzag(value as i64);
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, Self::I64);
+        self.zigzag(value);
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, Self::BINARY);
+        self.element_binary(value);
+    }
+
+    fn list(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, Self::LIST);
+        match len {
+            0..15 => self.bytes.push((len as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(0xf0 | kind);
+                self.varint(len as u64);
+            }
+        }
+    }
+
+    fn element_i32(&mut self, value: i32) {
+        self.zigzag(value as i64);
+    }
+
+    fn element_binary(&mut self, value: &[u8]) {
+        self.varint(value.len() as u64);
+        self.bytes.extend(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, Self::STRUCT);
+        self.last_fields.push(0);
+    }
+
+    // A struct in a list.
+    fn begin_element(&mut self) {
+        self.last_fields.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.bytes.push(0);
+        self.last_fields.pop();
+    }
+
+    fn end(mut self) -> Vec<u8> {
+        self.end_struct();
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempPath;
+
+    // Species 0 and 1 alternating, for four boids.
+    fn initial_traits() -> Vec<BoidTraits> {
+        (0..4).map(|i| BoidTraits::new(i % 2, [1.0; 4])).collect()
+    }
+
+    fn boid(i: usize, f: usize) -> Boid {
+        let v = (10 * f + i) as f32;
+        Boid::new(v, -v, v / 100.0, -v / 100.0)
+    }
+
+    // Three frames of three, four and then two boids, with their traits in every frame
+    // as emitters and sinks would leave them. In the last they're all species 2.
+    fn replay() -> Replay {
+        let frame = |f: usize, population: usize, traits: Option<Vec<BoidTraits>>| Frame {
+            step: 2 * f as u32,
+            traits,
+            boids: (0..population).map(|i| boid(i, f)).collect(),
+        };
+        Replay::from_frames(&initial_traits(), &[
+            frame(0, 3, Some(initial_traits()[..3].to_vec())),
+            frame(1, 4, Some(initial_traits())),
+            frame(2, 2, Some(vec![BoidTraits::new(2, [1.0; 4]); 2])),
+        ])
+    }
+
+    const POPULATIONS: [usize; 3] = [3, 4, 2];
+
+    fn species(f: usize, i: usize) -> u32 {
+        if f == 2 { 2 } else { i as u32 % 2 }
+    }
+
+    fn u16_at(bytes: &[u8], offset: usize) -> usize {
+        u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap()) as usize
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect()
+    }
+
+    fn ints(bytes: &[u8]) -> Vec<i32> {
+        bytes.chunks_exact(4).map(|b| i32::from_le_bytes(b.try_into().unwrap())).collect()
+    }
+
+    // The header's dict and the data after it.
+    fn split_npy(npy: &[u8]) -> (&str, &[u8]) {
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let len = u16_at(npy, 8);
+        (std::str::from_utf8(&npy[10..10 + len]).unwrap(), &npy[10 + len..])
+    }
+
+    // The value of `key` in a header's dict, as it's written.
+    fn npy_field<'a>(dict: &'a str, key: &str) -> &'a str {
+        let start = dict.find(&format!("'{key}': ")).unwrap() + key.len() + 4;
+        let end = start + dict[start..].find(if dict[start..].starts_with('(') { ')' } else { ',' }).unwrap();
+        &dict[start..=end]
+    }
+
+    // The dimensions of a header's shape, however its tuple is spaced.
+    fn npy_shape(dict: &str) -> Vec<usize> {
+        let shape = npy_field(dict, "shape");
+        shape[1..shape.len() - 1].split(',').map(str::trim).filter(|s| !s.is_empty()).map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn formats_are_named_by_extension() {
+        assert_eq!(ExportFormat::from_path("out/frames.CSV"), Some(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_path("a.b.npz"), Some(ExportFormat::Npz));
+        assert_eq!(ExportFormat::from_path("run.parquet"), Some(ExportFormat::Parquet));
+        assert_eq!(ExportFormat::from_path("run.traj"), None);
+        assert_eq!(ExportFormat::from_path("npy"), None);
+    }
+
+    #[test]
+    fn npy_headers_are_aligned() {
+        for shape in [&[5][..], &[3, 4, 4], &[123456789, 1000000]] {
+            let header = npy_header("f4", shape);
+            assert_eq!(header.len() % 64, 0);
+            let (dict, data) = split_npy(&header);
+            assert!(data.is_empty());
+            assert!(dict.ends_with('\n'));
+            assert!(dict.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': ("));
+        }
+        assert!(split_npy(&npy_header("u4", &[5])).0.contains("'shape': (5,)"));
+        assert!(split_npy(&npy_header("f4", &[3, 4, 4])).0.contains("'shape': (3, 4, 4)"));
+    }
+
+    #[test]
+    fn boids_are_padded_with_nan() {
+        let mut replay = replay();
+        let mut npy = npy_header("f4", &boids_shape(&replay));
+        write_boids(&mut replay, &mut npy).unwrap();
+
+        let (dict, data) = split_npy(&npy);
+        assert!(dict.contains("'shape': (3, 4, 4)"));
+        let values = floats(data);
+        assert_eq!(values.len(), 3 * 4 * 4);
+        for (f, frame) in values.chunks_exact(16).enumerate() {
+            for (i, values) in frame.chunks_exact(4).enumerate() {
+                match i < POPULATIONS[f] {
+                    true => assert_eq!(values, &bytemuck::cast::<Boid, [f32; 4]>(boid(i, f))),
+                    false => assert!(values.iter().all(|v| v.is_nan())),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn csv_has_a_file_per_frame() {
+        let dir = TempPath::new("csv");
+        write_csv(&mut replay(), &dir.join("frames.csv")).unwrap();
+        for (f, population) in POPULATIONS.into_iter().enumerate() {
+            let csv = std::fs::read_to_string(dir.join(&format!("frames_{f:06}.csv"))).unwrap();
+            let lines: Vec<&str> = csv.lines().collect();
+            assert_eq!(lines[0], "step,boid,species,x,y,vx,vy");
+            assert_eq!(lines.len(), population + 1);
+            for (i, line) in lines[1..].iter().enumerate() {
+                let [x, y, vx, vy]: [f32; 4] = bytemuck::cast(boid(i, f));
+                assert_eq!(*line, format!("{},{i},{},{x},{y},{vx},{vy}", 2 * f, species(f, i)));
+            }
+        }
+        assert!(!Path::new(&dir.join("frames_000003.csv")).exists());
+    }
+
+    // The entries of a stored zip, by name, checked against its central directory. Extra
+    // fields, such as the zip64 ones Python adds, are skipped.
+    fn unzip(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(zip, end), 0x06054b50);
+        let count = u16_at(zip, end + 10);
+        let mut entry = u32_at(zip, end + 16);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(zip, entry), 0x02014b50);
+            let crc = u32_at(zip, entry + 16) as u32;
+            let size = u32_at(zip, entry + 20);
+            assert_eq!(u32_at(zip, entry + 24), size, "entries are stored");
+            let name_len = u16_at(zip, entry + 28);
+            let name = String::from_utf8(zip[entry + 46..entry + 46 + name_len].to_vec()).unwrap();
+            let offset = u32_at(zip, entry + 42);
+
+            assert_eq!(u32_at(zip, offset), 0x04034b50);
+            assert_eq!(u16_at(zip, offset + 8), 0, "entries are stored");
+            assert_eq!(u32_at(zip, offset + 14) as u32, crc);
+            assert_eq!(&zip[offset + 30..offset + 30 + name_len], name.as_bytes());
+            let start = offset + 30 + name_len + u16_at(zip, offset + 28);
+            let data = &zip[start..start + size];
+            assert_eq!(crc32fast::hash(data), crc, "{name}'s checksum");
+
+            entries.push((name, data.to_vec()));
+            entry += 46 + name_len + u16_at(zip, entry + 30) + u16_at(zip, entry + 32);
+        }
+        entries
+    }
+
+    #[test]
+    fn npz_has_every_array() {
+        let dir = TempPath::new("npz");
+        let path = dir.join("run.npz");
+        write_npz(&mut replay(), &path).unwrap();
+        let entries = unzip(&std::fs::read(&path).unwrap());
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["boids.npy", "steps.npy", "population.npy", "species.npy"]);
+        let arrays: Vec<(&str, &[u8])> = entries.iter().map(|(_, npy)| split_npy(npy)).collect();
+
+        assert!(arrays[0].0.contains("'descr': '<f4'") && arrays[0].0.contains("'shape': (3, 4, 4)"));
+        assert_eq!(arrays[0].1.len(), 3 * 4 * 4 * 4);
+        assert!(arrays[1].0.contains("'descr': '<u4'") && arrays[1].0.contains("'shape': (3,)"));
+        assert_eq!(ints(arrays[1].1), [0, 2, 4]);
+        assert_eq!(ints(arrays[2].1), POPULATIONS.map(|p| p as i32));
+        assert!(arrays[3].0.contains("'descr': '<i4'") && arrays[3].0.contains("'shape': (3, 4)"));
+        assert_eq!(ints(arrays[3].1), [0, 1, 0, -1, 0, 1, 0, 1, 2, 2, -1, -1]);
+    }
+
+    #[test]
+    fn npz_matches_one_zipped_as_numpy_does() {
+        let expected = unzip(include_bytes!("../tests/data/replay.npz"));
+        let dir = TempPath::new("npz_reference");
+        let path = dir.join("run.npz");
+        write_npz(&mut replay(), &path).unwrap();
+        let entries = unzip(&std::fs::read(&path).unwrap());
+
+        assert_eq!(entries.len(), expected.len());
+        for ((name, npy), (expected_name, expected_npy)) in entries.iter().zip(&expected) {
+            assert_eq!(name, expected_name);
+            let (dict, data) = split_npy(npy);
+            let (expected_dict, expected_data) = split_npy(expected_npy);
+            assert_eq!(npy_field(dict, "descr"), npy_field(expected_dict, "descr"), "{name}");
+            assert_eq!(npy_field(dict, "fortran_order"), npy_field(expected_dict, "fortran_order"), "{name}");
+            assert_eq!(npy_shape(dict), npy_shape(expected_dict), "{name}");
+            assert_eq!(data, expected_data, "{name}");
+        }
+    }
+
+    // Just enough of Thrift's compact protocol to read back what `Thrift` and other
+    // Parquet writers write. Booleans and doubles are read as ints.
+    #[derive(Debug, PartialEq)]
+    enum Value {
+        Int(i64),
+        Binary(Vec<u8>),
+        List(Vec<Value>),
+        Struct(Vec<(i16, Value)>),
+    }
+
+    impl Value {
+        fn field(&self, id: i16) -> &Value {
+            self.get(id).unwrap_or_else(|| panic!("no field {id}"))
+        }
+
+        fn get(&self, id: i16) -> Option<&Value> {
+            let Value::Struct(fields) = self else { panic!("{self:?} isn't a struct") };
+            fields.iter().find(|(i, _)| *i == id).map(|(_, value)| value)
+        }
+
+        fn int(&self) -> i64 {
+            let Value::Int(value) = self else { panic!("{self:?} isn't an int") };
+            *value
+        }
+
+        fn list(&self) -> &[Value] {
+            let Value::List(values) = self else { panic!("{self:?} isn't a list") };
+            values
+        }
+
+        fn str(&self) -> &str {
+            let Value::Binary(bytes) = self else { panic!("{self:?} isn't binary") };
+            std::str::from_utf8(bytes).unwrap()
+        }
+    }
+
+    struct ThriftReader<'a> {
+        bytes: &'a [u8],
+    }
+
+    impl ThriftReader<'_> {
+        fn byte(&mut self) -> u8 {
+            let byte = self.bytes[0];
+            self.bytes = &self.bytes[1..];
+            byte
+        }
+
+        fn varint(&mut self) -> u64 {
+            let mut value = 0;
+            for shift in (0..64).step_by(7) {
+                let byte = self.byte();
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte & 0x80 == 0 { break; }
+            }
+            value
+        }
+
+        fn zigzag(&mut self) -> i64 {
+            let v = self.varint();
+            (v >> 1) as i64 ^ -((v & 1) as i64)
+        }
+
+        fn value(&mut self, kind: u8) -> Value {
+            match kind {
+                // booleans and bytes, as booleans in a struct are only the field's type
+                1..=3 => Value::Int(self.byte() as i64),
+                4 | Thrift::I32 | Thrift::I64 => Value::Int(self.zigzag()),
+                7 => {
+                    let (bytes, rest) = self.bytes.split_at(8);
+                    self.bytes = rest;
+                    Value::Int(i64::from_le_bytes(bytes.try_into().unwrap()))
+                }
+                Thrift::BINARY => {
+                    let len = self.varint() as usize;
+                    let (bytes, rest) = self.bytes.split_at(len);
+                    self.bytes = rest;
+                    Value::Binary(bytes.to_vec())
+                }
+                Thrift::LIST => {
+                    let header = self.byte();
+                    let len = match header >> 4 {
+                        15 => self.varint() as usize,
+                        len => len as usize,
+                    };
+                    Value::List((0..len).map(|_| self.value(header & 0xf)).collect())
+                }
+                Thrift::STRUCT => {
+                    let mut fields = Vec::new();
+                    let mut id = 0;
+                    loop {
+                        let header = self.byte();
+                        if header == 0 { break; }
+                        id = match header >> 4 {
+                            0 => self.zigzag() as i16,
+                            delta => id + delta as i16,
+                        };
+                        let value = match header & 0xf {
+                            kind @ (1 | 2) => Value::Int((kind == 1) as i64),
+                            kind => self.value(kind),
+                        };
+                        fields.push((id, value));
+                    }
+                    Value::Struct(fields)
+                }
+                kind => panic!("unexpected thrift type {kind}"),
+            }
+        }
+    }
+
+    fn parquet_metadata(file: &[u8]) -> Value {
+        assert_eq!(&file[..4], b"PAR1");
+        assert_eq!(&file[file.len() - 4..], b"PAR1");
+        let len = u32_at(file, file.len() - 8);
+        ThriftReader { bytes: &file[file.len() - 8 - len..file.len() - 8] }.value(Thrift::STRUCT)
+    }
+
+    // The header of the one page in each column chunk, in the first row group.
+    fn parquet_page_headers(file: &[u8], metadata: &Value) -> Vec<Value> {
+        metadata.field(4).list()[0].field(1).list().iter().map(|chunk| {
+            let offset = chunk.field(3).field(9).int() as usize;
+            ThriftReader { bytes: &file[offset..] }.value(Thrift::STRUCT)
+        }).collect()
+    }
+
+    // Every column, as the bytes of its values, and the rows in each row group.
+    fn read_parquet(file: &[u8]) -> (Vec<(String, Vec<u8>)>, Vec<i64>) {
+        let metadata = parquet_metadata(file);
+
+        let schema = metadata.field(2).list();
+        assert_eq!(schema[0].field(5).int(), PARQUET_COLUMNS.len() as i64);
+        for (element, (name, kind)) in schema[1..].iter().zip(PARQUET_COLUMNS) {
+            assert_eq!(element.field(4).str(), name);
+            assert_eq!(element.field(1).int(), kind as i64);
+        }
+
+        let mut columns: Vec<(String, Vec<u8>)> = PARQUET_COLUMNS.iter().map(|(name, _)| (name.to_string(), Vec::new())).collect();
+        let mut row_groups = Vec::new();
+        for row_group in metadata.field(4).list() {
+            let rows = row_group.field(3).int();
+            for (chunk, (_, values)) in row_group.field(1).list().iter().zip(&mut columns) {
+                let chunk = chunk.field(3);
+                assert_eq!(chunk.field(5).int(), rows);
+                let offset = chunk.field(9).int() as usize;
+                let size = chunk.field(7).int() as usize;
+                let mut reader = ThriftReader { bytes: &file[offset..offset + size] };
+                let page = reader.value(Thrift::STRUCT);
+                assert_eq!(page.field(1).int(), 0, "a data page");
+                assert_eq!(page.field(5).field(1).int(), rows);
+                let len = page.field(3).int() as usize;
+                assert_eq!(reader.bytes.len(), len);
+                values.extend(reader.bytes);
+            }
+            row_groups.push(rows);
+        }
+        assert_eq!(metadata.field(3).int(), row_groups.iter().sum::<i64>());
+        (columns, row_groups)
+    }
+
+    #[test]
+    fn parquet_has_a_row_per_boid_per_frame() {
+        let dir = TempPath::new("parquet");
+        for (row_group_rows, row_groups) in [(ROW_GROUP_ROWS, vec![9]), (3, vec![3, 4, 2]), (5, vec![7, 2])] {
+            let path = dir.join("run.parquet");
+            write_parquet(&mut replay(), &path, row_group_rows).unwrap();
+            let (columns, rows) = read_parquet(&std::fs::read(&path).unwrap());
+            assert_eq!(rows, row_groups);
+
+            let mut expected: Vec<Vec<f32>> = vec![Vec::new(); 8];
+            for (f, population) in POPULATIONS.into_iter().enumerate() {
+                for i in 0..population {
+                    let [x, y, vx, vy]: [f32; 4] = bytemuck::cast(boid(i, f));
+                    let row = [f as f32, 2.0 * f as f32, i as f32, species(f, i) as f32, x, y, vx, vy];
+                    for (column, value) in expected.iter_mut().zip(row) {
+                        column.push(value);
+                    }
+                }
+            }
+            for ((name, values), expected) in columns.iter().zip(expected) {
+                let values: Vec<f32> = match name.as_str() {
+                    "x" | "y" | "vx" | "vy" => floats(values),
+                    _ => ints(values).into_iter().map(|v| v as f32).collect(),
+                };
+                assert_eq!(values, expected, "column {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn parquet_matches_one_from_the_parquet_crate() {
+        let expected: &[u8] = include_bytes!("../tests/data/replay.parquet");
+        let dir = TempPath::new("parquet_reference");
+        let path = dir.join("run.parquet");
+        write_parquet(&mut replay(), &path, ROW_GROUP_ROWS).unwrap();
+        let file = std::fs::read(&path).unwrap();
+        assert_eq!(read_parquet(&file), read_parquet(expected));
+
+        // the same types, names and repetition for the schema, and the same types,
+        // encodings, paths, codec and value counts for the columns and their pages
+        let metadata = parquet_metadata(&file);
+        let expected_metadata = parquet_metadata(expected);
+        let schema = metadata.field(2).list();
+        let expected_schema = expected_metadata.field(2).list();
+        assert_eq!(schema.len(), expected_schema.len());
+        for (element, expected) in schema.iter().zip(expected_schema) {
+            for id in [1, 3, 4, 5] {
+                assert_eq!(element.get(id), expected.get(id), "schema field {id} of {element:?}");
+            }
+        }
+        let chunks = metadata.field(4).list()[0].field(1).list();
+        let expected_chunks = expected_metadata.field(4).list()[0].field(1).list();
+        for (chunk, expected) in chunks.iter().zip(expected_chunks) {
+            for id in [1, 2, 3, 4, 5] {
+                assert_eq!(chunk.field(3).get(id), expected.field(3).get(id), "column field {id} of {chunk:?}");
+            }
+        }
+        let pages = parquet_page_headers(&file, &metadata);
+        let expected_pages = parquet_page_headers(expected, &expected_metadata);
+        for (page, expected) in pages.iter().zip(&expected_pages) {
+            assert_eq!(page.get(1), expected.get(1));
+            for id in [1, 2, 3, 4] {
+                assert_eq!(page.field(5).get(id), expected.field(5).get(id), "data page field {id} of {page:?}");
+            }
+        }
+    }
+}
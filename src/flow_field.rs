@@ -0,0 +1,180 @@
+use rand::prelude::*;
+
+use crate::image::Image;
+use crate::overlay::OverlayVertex;
+
+// A 2D vector field covering the square `[-extent, extent]^2` of world space, which
+// pushes boids around like wind or a current. Vectors are at most unit length; the
+// push strength is `SimParams::flow_weight`.
+pub struct FlowField {
+    pub width: u32,
+    pub height: u32,
+    pub vectors: Vec<[f32; 2]>,
+}
+
+impl FlowField {
+    // A 1x1 field with no flow, bound when the scenario doesn't have one.
+    pub fn still() -> Self {
+        Self { width: 1, height: 1, vectors: vec![[0.0, 0.0]] }
+    }
+
+    // Red and green channels are the x and y components, remapped from 0..255 to -1..1,
+    // so a flat 128 grey image means no flow.
+    pub fn from_png(path: &str) -> anyhow::Result<Self> {
+        let image = Image::load_png(path)?;
+        let mut vectors = Vec::with_capacity(image.pixels.len());
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let [r, g, _, _] = image.pixel(x, y);
+                vectors.push([r as f32 / 127.5 - 1.0, g as f32 / 127.5 - 1.0]);
+            }
+        }
+        let mut field = Self { width: image.width, height: image.height, vectors };
+        field.normalize();
+        Ok(field)
+    }
+
+    // Divergence free flow, from the curl of gradient noise with `scale` features
+    // across the field.
+    pub fn curl_noise(resolution: u32, scale: f32, seed: u64) -> Self {
+        let noise = GradientNoise::new(seed);
+        let potential = |x: f32, y: f32| noise.sample(x * scale, y * scale);
+
+        let h = 0.5 / resolution as f32;
+        let mut vectors = Vec::with_capacity((resolution * resolution) as usize);
+        for y in 0..resolution {
+            for x in 0..resolution {
+                let u = (x as f32 + 0.5) / resolution as f32;
+                let v = (y as f32 + 0.5) / resolution as f32;
+                let dx = (potential(u + h, v) - potential(u - h, v)) / (2.0 * h);
+                let dy = (potential(u, v + h) - potential(u, v - h)) / (2.0 * h);
+                vectors.push([dy, -dx]);
+            }
+        }
+        let mut field = Self { width: resolution, height: resolution, vectors };
+        field.normalize();
+        field
+    }
+
+    fn normalize(&mut self) {
+        let max = self.vectors.iter()
+            .map(|[x, y]| f32::sqrt(x * x + y * y))
+            .fold(0.0, f32::max);
+        if max > 0.0 {
+            for v in &mut self.vectors {
+                *v = [v[0] / max, v[1] / max];
+            }
+        }
+    }
+
+    // Nearest texel at normalized coordinates `(u, v)`, for drawing.
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 2] {
+        let x = ((u * self.width as f32) as u32).min(self.width - 1);
+        let y = ((v * self.height as f32) as u32).min(self.height - 1);
+        self.vectors[(y * self.width + x) as usize]
+    }
+
+    // A `grid x grid` field of arrows for the overlay, each as long as a cell at full
+    // strength.
+    pub fn arrow_lines(&self, extent: f32, grid: u32) -> Vec<OverlayVertex> {
+        let color = [0.5, 0.5, 0.6, 0.35];
+        let cell = 2.0 * extent / grid as f32;
+        let mut lines = Vec::with_capacity((grid * grid * 6) as usize);
+        for y in 0..grid {
+            for x in 0..grid {
+                let u = (x as f32 + 0.5) / grid as f32;
+                let v = (y as f32 + 0.5) / grid as f32;
+                let [dx, dy] = self.sample(u, v);
+                if dx == 0.0 && dy == 0.0 { continue; }
+
+                let tail = [(2.0 * u - 1.0) * extent, (2.0 * v - 1.0) * extent];
+                let head = [tail[0] + dx * cell, tail[1] + dy * cell];
+                let (bx, by) = (-dx * cell * 0.3, -dy * cell * 0.3);
+                let (px, py) = (-by * 0.5, bx * 0.5);
+                lines.extend_from_slice(&[
+                    OverlayVertex::new(tail, color),
+                    OverlayVertex::new(head, color),
+                    OverlayVertex::new(head, color),
+                    OverlayVertex::new([head[0] + bx + px, head[1] + by + py], color),
+                    OverlayVertex::new(head, color),
+                    OverlayVertex::new([head[0] + bx - px, head[1] + by - py], color),
+                ]);
+            }
+        }
+        lines
+    }
+
+    pub fn create_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(
+            &wgpu::TextureDescriptor {
+                label: Some("Flow Field Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                // not filterable without an optional feature, so `cs_main` filters by hand
+                format: wgpu::TextureFormat::Rg32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            }
+        );
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&self.vectors),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.width * std::mem::size_of::<[f32; 2]>() as u32),
+                rows_per_image: Some(self.height),
+            },
+            size,
+        );
+        texture
+    }
+}
+
+// Classic 2D Perlin noise with a seeded permutation table.
+struct GradientNoise {
+    permutation: [u8; 512],
+}
+
+impl GradientNoise {
+    fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut rand::rngs::StdRng::seed_from_u64(seed));
+        let mut permutation = [0; 512];
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = table[i % 256];
+        }
+        Self { permutation }
+    }
+
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let (xf, yf) = (x.floor(), y.floor());
+        let (xi, yi) = ((xf as i32 & 255) as usize, (yf as i32 & 255) as usize);
+        let (x, y) = (x - xf, y - yf);
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (u, v) = (fade(x), fade(y));
+
+        let p = &self.permutation;
+        let corner = |i: usize, j: usize, dx: f32, dy: f32| {
+            let a = p[p[xi + i] as usize + yi + j] as f32 / 256.0 * std::f32::consts::TAU;
+            a.cos() * dx + a.sin() * dy
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        lerp(
+            lerp(corner(0, 0, x, y), corner(1, 0, x - 1.0, y), u),
+            lerp(corner(0, 1, x, y - 1.0), corner(1, 1, x - 1.0, y - 1.0), u),
+            v,
+        )
+    }
+}
@@ -0,0 +1,53 @@
+// Pipelines that draw `fullscreen.wgsl`'s single triangle with the given fragment shader.
+pub fn create_fullscreen_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    target: wgpu::ColorTargetState,
+    multisample: wgpu::MultisampleState,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(
+        &wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &[
+                bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        }
+    );
+    device.create_render_pipeline(
+        &wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: fragment_entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[
+                    Some(target),
+                ],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample,
+            multiview: None,
+            cache: None,
+        }
+    )
+}
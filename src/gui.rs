@@ -0,0 +1,650 @@
+use bytemuck::Zeroable;
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+// A small immediate-mode GUI drawn over everything else. Every frame the widgets are
+// declared again between `begin` and `end`, each one drawing itself and reporting
+// whether it was used, and they're stacked top to bottom in panels pinned to the
+// window's top corners. Text uses a built-in 5x7 bitmap font, so nothing has to be
+// loaded.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GuiVertex {
+    // in pixels from the window's top left corner
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Rect {
+    min: [f32; 2],
+    max: [f32; 2],
+}
+
+impl Rect {
+    fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { min: [x, y], max: [x + width, y + height] }
+    }
+
+    fn contains(&self, [x, y]: [f32; 2]) -> bool {
+        x >= self.min[0] && x < self.max[0] && y >= self.min[1] && y < self.max[1]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+}
+
+pub struct Gui {
+    screen_size: [f32; 2],
+
+    mouse: [f32; 2],
+    mouse_down: bool,
+    // set by a press and cleared once a frame has seen it
+    clicked: bool,
+    // the widget being dragged, by the order it's declared in
+    active: Option<usize>,
+    next_id: usize,
+
+    // where the next widget goes
+    x: f32,
+    y: f32,
+    // where the current panel's background goes in `vertices`, once its size is known
+    panel_start: usize,
+    panels: Vec<Rect>,
+    // where the panels were last frame, which decides what input is ours
+    last_panels: Vec<Rect>,
+    vertices: Vec<GuiVertex>,
+    vertex_count: u32,
+
+    vertex_buffer: wgpu::Buffer,
+    screen_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+const MAX_GUI_VERTICES: usize = 1 << 15;
+
+// Glyphs are drawn this many pixels per font pixel.
+const SCALE: f32 = 2.0;
+const CHAR_WIDTH: f32 = 6.0 * SCALE;
+const CHAR_HEIGHT: f32 = 8.0 * SCALE;
+const ROW_HEIGHT: f32 = CHAR_HEIGHT + 8.0;
+const GRAPH_HEIGHT: f32 = 48.0;
+const PADDING: f32 = 8.0;
+const LABEL_WIDTH: f32 = 13.0 * CHAR_WIDTH;
+const CONTROL_WIDTH: f32 = 240.0;
+const PANEL_WIDTH: f32 = PADDING + LABEL_WIDTH + CONTROL_WIDTH + PADDING;
+
+// Colours are linear, since the surface is sRGB.
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const CONTROL_COLOR: [f32; 4] = [0.05, 0.05, 0.08, 1.0];
+const HOVER_COLOR: [f32; 4] = [0.1, 0.1, 0.16, 1.0];
+const FILL_COLOR: [f32; 4] = [0.05, 0.2, 0.45, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const HEADING_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+
+impl Gui {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let vertex_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("GUI Vertex Buffer"),
+                size: (MAX_GUI_VERTICES * std::mem::size_of::<GuiVertex>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+        let screen_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("GUI Screen Buffer"),
+                size: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+
+        let font_view = create_font_texture(device, queue)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let font_sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("GUI Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            }
+        );
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("GUI Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: screen_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&font_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&font_sampler),
+                    },
+                ],
+            }
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("GUI Pipeline Layout"),
+                bind_group_layouts: &[
+                    &bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("gui.wgsl"));
+
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("GUI Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<GuiVertex>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4],
+                        },
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[
+                        Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }),
+                    ],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            }
+        );
+
+        Self {
+            screen_size: [1.0, 1.0],
+
+            mouse: [0.0, 0.0],
+            mouse_down: false,
+            clicked: false,
+            active: None,
+            next_id: 0,
+
+            x: 0.0,
+            y: 0.0,
+            panel_start: 0,
+            panels: Vec::new(),
+            last_panels: Vec::new(),
+            vertices: Vec::new(),
+            vertex_count: 0,
+
+            vertex_buffer,
+            screen_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    // Returns true if the event was meant for the GUI, so shouldn't be passed on: clicks
+    // on a panel, and anything while a slider is being dragged.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse = [position.x as f32, position.y as f32];
+                self.active.is_some()
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
+                if !self.last_panels.iter().any(|panel| panel.contains(self.mouse)) { return false; }
+                self.mouse_down = true;
+                self.clicked = true;
+                true
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left, .. } => {
+                let ours = self.mouse_down;
+                self.mouse_down = false;
+                self.active = None;
+                ours
+            }
+            _ => false,
+        }
+    }
+
+    pub fn begin(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        self.screen_size = [size.width as f32, size.height as f32];
+        self.vertices.clear();
+        self.panels.clear();
+        self.next_id = 0;
+    }
+
+    pub fn end(&mut self, queue: &wgpu::Queue) {
+        std::mem::swap(&mut self.panels, &mut self.last_panels);
+        if self.last_panels.is_empty() {
+            // nothing can be dragged with no panels to drag it in
+            self.active = None;
+        }
+        self.clicked = false;
+
+        let vertices = &self.vertices[..self.vertices.len().min(MAX_GUI_VERTICES)];
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        queue.write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(&self.screen_size));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        if self.vertex_count == 0 { return; }
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("GUI Pass"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        }
+                    })
+                ],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            }
+        );
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+
+    pub fn begin_panel(&mut self, corner: Corner) {
+        self.x = match corner {
+            Corner::TopLeft => PADDING,
+            Corner::TopRight => self.screen_size[0] - PANEL_WIDTH + PADDING,
+        };
+        self.y = PADDING;
+        // the background, filled in by `end_panel` once its height is known
+        self.panel_start = self.vertices.len();
+        self.vertices.extend_from_slice(&[GuiVertex::zeroed(); 6]);
+    }
+
+    pub fn end_panel(&mut self) {
+        let panel = Rect::new(self.x - PADDING, 0.0, PANEL_WIDTH, self.y + PADDING);
+        let mut background = Vec::new();
+        push_rect(&mut background, panel, PANEL_COLOR);
+        self.vertices[self.panel_start..self.panel_start + 6].copy_from_slice(&background);
+        self.panels.push(panel);
+    }
+
+    pub fn heading(&mut self, text: &str) {
+        self.text(self.x, self.y, text, HEADING_COLOR);
+        self.y += ROW_HEIGHT;
+    }
+
+    pub fn label(&mut self, text: &str) {
+        self.text(self.x, self.y, text, TEXT_COLOR);
+        self.y += ROW_HEIGHT;
+    }
+
+    // A bar for each of the last `count` values, scaled so `max` reaches the top.
+    pub fn graph(&mut self, values: impl ExactSizeIterator<Item = f32>, count: usize, max: f32) {
+        let rect = Rect::new(self.x, self.y, PANEL_WIDTH - 2.0 * PADDING, GRAPH_HEIGHT);
+        push_rect(&mut self.vertices, rect, CONTROL_COLOR);
+        let width = (rect.max[0] - rect.min[0]) / count as f32;
+        // the newest value is always on the right
+        let first = count.saturating_sub(values.len());
+        for (i, value) in values.enumerate() {
+            let height = (value / max).clamp(0.0, 1.0) * GRAPH_HEIGHT;
+            let x = rect.min[0] + (first + i) as f32 * width;
+            push_rect(&mut self.vertices, Rect::new(x, rect.max[1] - height, width, height), FILL_COLOR);
+        }
+        self.y += GRAPH_HEIGHT + ROW_HEIGHT - CHAR_HEIGHT;
+    }
+
+    // A value that can be dragged anywhere in `min..=max`. Returns true if it changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = self.next_id();
+        let track = self.control_rect();
+        if self.clicked && track.contains(self.mouse) {
+            self.active = Some(id);
+        }
+
+        let mut changed = false;
+        if self.active == Some(id) {
+            let t = (self.mouse[0] - track.min[0]) / (track.max[0] - track.min[0]);
+            let new_value = min + t.clamp(0.0, 1.0) * (max - min);
+            changed = new_value != *value;
+            *value = new_value;
+        }
+
+        self.text(self.x, self.y, label, TEXT_COLOR);
+        self.control(track);
+        let t = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+        let fill = Rect { min: track.min, max: [track.min[0] + t * (track.max[0] - track.min[0]), track.max[1]] };
+        push_rect(&mut self.vertices, fill, FILL_COLOR);
+        self.text(track.min[0] + 4.0, self.y, &format_value(*value), TEXT_COLOR);
+
+        self.y += ROW_HEIGHT;
+        changed
+    }
+
+    // A labelled button showing the current choice, for cycling through the options.
+    // Returns true if it was clicked.
+    pub fn choice(&mut self, label: &str, value: &str) -> bool {
+        self.next_id();
+        let rect = self.control_rect();
+        let clicked = self.clicked && rect.contains(self.mouse);
+
+        self.text(self.x, self.y, label, TEXT_COLOR);
+        self.control(rect);
+        self.text(rect.min[0] + 4.0, self.y, value, TEXT_COLOR);
+
+        self.y += ROW_HEIGHT;
+        clicked
+    }
+
+    // A row of equally wide buttons. Returns the index of the one clicked, if any.
+    pub fn buttons(&mut self, labels: &[&str]) -> Option<usize> {
+        let gap = 4.0;
+        let width = (PANEL_WIDTH - 2.0 * PADDING - gap * (labels.len() - 1) as f32) / labels.len() as f32;
+        let mut clicked = None;
+        for (i, label) in labels.iter().enumerate() {
+            self.next_id();
+            let rect = Rect::new(self.x + i as f32 * (width + gap), self.y, width, CHAR_HEIGHT + 4.0);
+            if self.clicked && rect.contains(self.mouse) {
+                clicked = Some(i);
+            }
+            self.control(rect);
+            let text_width = label.len() as f32 * CHAR_WIDTH;
+            self.text(rect.min[0] + (width - text_width) / 2.0, self.y, label, TEXT_COLOR);
+        }
+        self.y += ROW_HEIGHT;
+        clicked
+    }
+
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn control_rect(&self) -> Rect {
+        Rect::new(self.x + LABEL_WIDTH, self.y, CONTROL_WIDTH, CHAR_HEIGHT + 4.0)
+    }
+
+    // A control's background, lit up when the mouse is over it.
+    fn control(&mut self, rect: Rect) {
+        let color = if rect.contains(self.mouse) { HOVER_COLOR } else { CONTROL_COLOR };
+        push_rect(&mut self.vertices, rect, color);
+    }
+
+    fn text(&mut self, x: f32, y: f32, text: &str, color: [f32; 4]) {
+        let y = y + 2.0;
+        for (i, c) in text.chars().enumerate() {
+            let glyph = match c {
+                ' '..='~' => c as u32 - ' ' as u32,
+                _ => '?' as u32 - ' ' as u32,
+            };
+            let rect = Rect::new(x + i as f32 * CHAR_WIDTH, y, CHAR_WIDTH, CHAR_HEIGHT);
+            push_quad(&mut self.vertices, rect, glyph_uv(glyph), color);
+        }
+    }
+}
+
+// Three significant figures or so, without the trailing noise of `{}`.
+fn format_value(value: f32) -> String {
+    match value.abs() {
+        v if v >= 100.0 => format!("{value:.0}"),
+        v if v >= 10.0 => format!("{value:.1}"),
+        _ => format!("{value:.2}"),
+    }
+}
+
+fn push_rect(vertices: &mut Vec<GuiVertex>, rect: Rect, color: [f32; 4]) {
+    push_quad(vertices, rect, glyph_uv(SOLID_GLYPH), color);
+}
+
+fn push_quad(vertices: &mut Vec<GuiVertex>, rect: Rect, uv: Rect, color: [f32; 4]) {
+    let corner = |x: usize, y: usize| GuiVertex {
+        position: [[rect.min[0], rect.max[0]][x], [rect.min[1], rect.max[1]][y]],
+        uv: [[uv.min[0], uv.max[0]][x], [uv.min[1], uv.max[1]][y]],
+        color,
+    };
+    vertices.extend_from_slice(&[
+        corner(0, 0), corner(0, 1), corner(1, 1),
+        corner(0, 0), corner(1, 1), corner(1, 0),
+    ]);
+}
+
+// The font texture has the glyphs for ' ' to '~' in a grid, each in a 6x8 cell with
+// the 5x7 glyph in its top left.
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 6;
+const CELL_WIDTH: u32 = 6;
+const CELL_HEIGHT: u32 = 8;
+// The last cell is filled in, for drawing solid rectangles.
+const SOLID_GLYPH: u32 = 95;
+
+fn glyph_uv(glyph: u32) -> Rect {
+    let width = (ATLAS_COLUMNS * CELL_WIDTH) as f32;
+    let height = (ATLAS_ROWS * CELL_HEIGHT) as f32;
+    let x = (glyph % ATLAS_COLUMNS * CELL_WIDTH) as f32;
+    let y = (glyph / ATLAS_COLUMNS * CELL_HEIGHT) as f32;
+    if glyph == SOLID_GLYPH {
+        // any texel inside the block will do
+        return Rect::new((x + 2.5) / width, (y + 3.5) / height, 0.0, 0.0);
+    }
+    Rect::new(x / width, y / height, CELL_WIDTH as f32 / width, CELL_HEIGHT as f32 / height)
+}
+
+fn create_font_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let width = ATLAS_COLUMNS * CELL_WIDTH;
+    let height = ATLAS_ROWS * CELL_HEIGHT;
+    let mut pixels = vec![0u8; (width * height) as usize];
+    for (glyph, columns) in FONT.iter().enumerate() {
+        let x0 = glyph as u32 % ATLAS_COLUMNS * CELL_WIDTH;
+        let y0 = glyph as u32 / ATLAS_COLUMNS * CELL_HEIGHT;
+        for (dx, column) in columns.iter().enumerate() {
+            for dy in 0..7 {
+                if column & (1 << dy) != 0 {
+                    pixels[((y0 + dy) * width + x0 + dx as u32) as usize] = 255;
+                }
+            }
+        }
+    }
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(
+        &wgpu::TextureDescriptor {
+            label: Some("GUI Font Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        }
+    );
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+    texture
+}
+
+// The classic 5x7 font, one byte per column from left to right with the top row in the
+// lowest bit, for ' ' to '~' and then a solid block.
+const FONT: [[u8; 5]; 96] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x14, 0x08, 0x3e, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+    [0x7f, 0x7f, 0x7f, 0x7f, 0x7f], // solid
+];
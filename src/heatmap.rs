@@ -0,0 +1,407 @@
+use wgpu::util::DeviceExt;
+
+use crate::boid::Boid;
+use crate::coloring::ColorRamp;
+use crate::fullscreen::create_fullscreen_pipeline;
+use crate::culling::SPLAT_ARGS;
+
+// Boid density, splatted into a float texture, blurred and mapped through a colour ramp.
+// Drawn underneath the boids, or instead of them when there are too many to make out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeatmapMode {
+    Off,
+    Underlay,
+    Only,
+}
+
+impl HeatmapMode {
+    pub const ALL: [HeatmapMode; 3] = [HeatmapMode::Off, HeatmapMode::Underlay, HeatmapMode::Only];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HeatmapMode::Off => "off",
+            HeatmapMode::Underlay => "underlay",
+            HeatmapMode::Only => "only",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct HeatmapUniform {
+    ramp: u32,
+    exposure: f32,
+    _padding: [u32; 2],
+}
+
+const DENSITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+// Each splat is a quad, as two triangles.
+pub const SPLAT_VERTICES: u32 = 6;
+
+// How quickly density saturates to the top of the ramp.
+const EXPOSURE: f32 = 0.5;
+
+pub struct Heatmap {
+    mode: HeatmapMode,
+
+    // the splats go into `density_views[0]`, the horizontal blur into `[1]` and the
+    // vertical blur back into `[0]`
+    density_views: [wgpu::TextureView; 2],
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_groups: [wgpu::BindGroup; 2],
+
+    splat_pipeline: wgpu::RenderPipeline,
+    blur_x_pipeline: wgpu::RenderPipeline,
+    blur_y_pipeline: wgpu::RenderPipeline,
+    tonemap_pipeline: wgpu::RenderPipeline,
+}
+
+impl Heatmap {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        multisample: wgpu::MultisampleState,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        ramp: ColorRamp,
+    ) -> Self {
+        let sampler = device.create_sampler(
+            &wgpu::SamplerDescriptor {
+                label: Some("Heatmap Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }
+        );
+
+        let uniform = HeatmapUniform { ramp: ramp as u32, exposure: EXPOSURE, _padding: [0; 2] };
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Heatmap Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Heatmap Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }
+        );
+
+        let density_views = create_density_views(device, config);
+        let bind_groups = create_bind_groups(device, &bind_group_layout, &density_views, &sampler, &uniform_buffer);
+
+        let splat_shader = device.create_shader_module(wgpu::include_wgsl!("heatmap_splat.wgsl"));
+        let splat_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Heatmap Splat Pipeline Layout"),
+                bind_group_layouts: &[
+                    camera_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+        let splat_pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Heatmap Splat Pipeline"),
+                layout: Some(&splat_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &splat_shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<Boid>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                        },
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &splat_shader,
+                    entry_point: "fs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[
+                        Some(wgpu::ColorTargetState {
+                            format: DENSITY_FORMAT,
+                            blend: Some(wgpu::BlendState {
+                                color: wgpu::BlendComponent {
+                                    src_factor: wgpu::BlendFactor::One,
+                                    dst_factor: wgpu::BlendFactor::One,
+                                    operation: wgpu::BlendOperation::Add,
+                                },
+                                alpha: wgpu::BlendComponent::REPLACE,
+                            }),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }),
+                    ],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            }
+        );
+
+        let heatmap_shader = device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("heatmap.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(
+                    include_str!("fullscreen.wgsl"),
+                    include_str!("ramps.wgsl"),
+                    include_str!("heatmap.wgsl"),
+                ).into()),
+            }
+        );
+        let blur_x_pipeline = create_fullscreen_pipeline(
+            device,
+            "Heatmap Blur X Pipeline",
+            &bind_group_layout,
+            &heatmap_shader,
+            "fs_blur_x",
+            wgpu::ColorTargetState {
+                format: DENSITY_FORMAT,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            wgpu::MultisampleState::default(),
+        );
+        let blur_y_pipeline = create_fullscreen_pipeline(
+            device,
+            "Heatmap Blur Y Pipeline",
+            &bind_group_layout,
+            &heatmap_shader,
+            "fs_blur_y",
+            wgpu::ColorTargetState {
+                format: DENSITY_FORMAT,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            wgpu::MultisampleState::default(),
+        );
+        let tonemap_pipeline = create_fullscreen_pipeline(
+            device,
+            "Heatmap Tonemap Pipeline",
+            &bind_group_layout,
+            &heatmap_shader,
+            "fs_tonemap",
+            wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            multisample,
+        );
+
+        Self {
+            mode: HeatmapMode::Off,
+
+            density_views,
+            sampler,
+            uniform_buffer,
+            bind_group_layout,
+            bind_groups,
+
+            splat_pipeline,
+            blur_x_pipeline,
+            blur_y_pipeline,
+            tonemap_pipeline,
+        }
+    }
+
+    pub fn mode(&self) -> HeatmapMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: HeatmapMode) {
+        self.mode = mode;
+    }
+
+    pub fn set_ramp(&self, queue: &wgpu::Queue, ramp: ColorRamp) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[ramp as u32]));
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.density_views = create_density_views(device, config);
+        self.bind_groups = create_bind_groups(device, &self.bind_group_layout, &self.density_views, &self.sampler, &self.uniform_buffer);
+    }
+
+    // Splats the live boids in `boids_buffer` and draws the tone mapped density into
+    // `target`, whatever the mode, since zooming far out shows the heatmap even when it's
+    // off. `draw_args` are `Culling`'s.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: wgpu::RenderPassColorAttachment,
+        boids_buffer: &wgpu::Buffer,
+        camera_bind_group: &wgpu::BindGroup,
+        draw_args: &wgpu::Buffer,
+    ) {
+        {
+            let mut render_pass = begin_pass(encoder, "Heatmap Splat Pass", &self.density_views[0], wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT));
+            render_pass.set_pipeline(&self.splat_pipeline);
+            render_pass.set_bind_group(0, camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, boids_buffer.slice(..));
+            render_pass.draw_indirect(draw_args, SPLAT_ARGS);
+        }
+        {
+            let mut render_pass = begin_pass(encoder, "Heatmap Blur X Pass", &self.density_views[1], wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT));
+            render_pass.set_pipeline(&self.blur_x_pipeline);
+            render_pass.set_bind_group(0, &self.bind_groups[0], &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        {
+            let mut render_pass = begin_pass(encoder, "Heatmap Blur Y Pass", &self.density_views[0], wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT));
+            render_pass.set_pipeline(&self.blur_y_pipeline);
+            render_pass.set_bind_group(0, &self.bind_groups[1], &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("Heatmap Tonemap Pass"),
+                color_attachments: &[
+                    Some(target),
+                ],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            }
+        );
+        render_pass.set_pipeline(&self.tonemap_pipeline);
+        render_pass.set_bind_group(0, &self.bind_groups[0], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(
+        &wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    }
+                })
+            ],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        }
+    )
+}
+
+fn create_density_views(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> [wgpu::TextureView; 2] {
+    [0, 1].map(|i| {
+        let texture = device.create_texture(
+            &wgpu::TextureDescriptor {
+                label: Some(format!("Heatmap Density Texture {}", i).as_str()),
+                size: wgpu::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: DENSITY_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            }
+        );
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    })
+}
+
+// `bind_groups[i]` reads from `density_views[i]`.
+fn create_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    density_views: &[wgpu::TextureView; 2],
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+) -> [wgpu::BindGroup; 2] {
+    [0, 1].map(|i| device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            label: Some(format!("Heatmap Bind Group {}", i).as_str()),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&density_views[i]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        }
+    ))
+}
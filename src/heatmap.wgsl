@@ -0,0 +1,39 @@
+struct Heatmap {
+    ramp: u32,
+    exposure: f32,
+}
+
+@group(0) @binding(0) var density: texture_2d<f32>;
+@group(0) @binding(1) var density_sampler: sampler;
+@group(0) @binding(2) var<uniform> heatmap: Heatmap;
+
+// 9 tap gaussian, split into a horizontal and a vertical pass.
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> f32 {
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    let step = direction / vec2<f32>(textureDimensions(density));
+    var sum = textureSample(density, density_sampler, uv).r * weights[0];
+    for(var i = 1; i < 5; i++) {
+        let offset = step * f32(i);
+        sum += textureSample(density, density_sampler, uv + offset).r * weights[i];
+        sum += textureSample(density, density_sampler, uv - offset).r * weights[i];
+    }
+    return sum;
+}
+
+@fragment
+fn fs_blur_x(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(blur(in.uv, vec2<f32>(1.0, 0.0)), 0.0, 0.0, 0.0);
+}
+
+@fragment
+fn fs_blur_y(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(blur(in.uv, vec2<f32>(0.0, 1.0)), 0.0, 0.0, 0.0);
+}
+
+// Maps density through the ramp, fading into the background where there's none.
+@fragment
+fn fs_tonemap(in: VertexOutput) -> @location(0) vec4<f32> {
+    let d = textureSample(density, density_sampler, in.uv).r;
+    let t = 1.0 - exp(-d * heatmap.exposure);
+    return vec4<f32>(ramp(heatmap.ramp, t), t);
+}
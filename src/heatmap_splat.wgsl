@@ -0,0 +1,42 @@
+struct SplatInstance {
+    @location(0) pos: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) offset: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera_mat: mat3x3<f32>;
+
+// Radius of a boid's footprint in the density texture, in world units.
+const SPLAT_RADIUS = 6.0;
+
+// Each boid adds a gaussian bump to the density texture, drawn as a quad.
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: SplatInstance,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>( 1.0, -1.0), vec2<f32>( 1.0,  1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>( 1.0,  1.0), vec2<f32>(-1.0,  1.0),
+    );
+    let corner = corners[vertex_index];
+
+    let clip_position = camera_mat * vec3<f32>(instance.pos + corner * SPLAT_RADIUS, 1.0);
+    out.clip_position = vec4<f32>(clip_position.xy, 0.0, 1.0);
+    out.offset = corner;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let r2 = dot(in.offset, in.offset);
+    if(r2 > 1.0) { discard; }
+    return vec4<f32>(exp(-4.0 * r2), 0.0, 0.0, 0.0);
+}
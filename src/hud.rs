@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::gui::{Corner, Gui};
+use crate::profiler::{Pass, Profiler};
+use crate::readback::Readback;
+use crate::analytics::{self, Analytics};
+
+// Frames kept for the frame time graph.
+const HISTORY: usize = 120;
+// How often the rates are worked out again, and how often they're logged.
+const RATE_INTERVAL: Duration = Duration::from_secs(1);
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+// The frame time graph is never scaled to less than this, in milliseconds.
+const GRAPH_MIN: f32 = 1000.0 / 30.0;
+
+// Performance numbers shown in the top right corner, and logged every few seconds
+// whether they're shown or not.
+pub struct Hud {
+    last_frame: Instant,
+    // in milliseconds, most recent last
+    frame_times: VecDeque<f32>,
+
+    rate_start: Instant,
+    frames: u32,
+    steps: u32,
+    fps: f32,
+    steps_per_second: f32,
+    last_log: Instant,
+
+    population: Option<u32>,
+    population_readback: Readback,
+}
+
+impl Hud {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let now = Instant::now();
+        Self {
+            last_frame: now,
+            frame_times: VecDeque::with_capacity(HISTORY),
+
+            rate_start: now,
+            frames: 0,
+            steps: 0,
+            fps: 0.0,
+            steps_per_second: 0.0,
+            last_log: now,
+
+            population: None,
+            population_readback: Readback::new(device, "Population Readback Buffer", std::mem::size_of::<u32>() as wgpu::BufferAddress),
+        }
+    }
+
+    // Call once per frame drawn, saying whether the boids took a step for it.
+    pub fn frame(&mut self, stepped: bool, profiler: Option<&Profiler>) {
+        let now = Instant::now();
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back((now - self.last_frame).as_secs_f32() * 1000.0);
+        self.last_frame = now;
+
+        self.frames += 1;
+        self.steps += stepped as u32;
+        let elapsed = now - self.rate_start;
+        if elapsed >= RATE_INTERVAL {
+            self.fps = self.frames as f32 / elapsed.as_secs_f32();
+            self.steps_per_second = self.steps as f32 / elapsed.as_secs_f32();
+            self.frames = 0;
+            self.steps = 0;
+            self.rate_start = now;
+        }
+
+        if now - self.last_log >= LOG_INTERVAL {
+            self.last_log = now;
+            let mut line = format!(
+                "{:.1} fps, {:.1} steps/s, {} boids",
+                self.fps,
+                self.steps_per_second,
+                self.population.map_or("?".to_string(), |p| p.to_string()),
+            );
+            for pass in Pass::ALL {
+                if let Some(ms) = profiler.and_then(|p| p.timing(pass)) {
+                    line += &format!(", {} {ms:.3} ms", pass.name());
+                }
+            }
+            log::info!("{line}");
+        }
+    }
+
+    // The frame time averaged over the graph, in milliseconds.
+    pub fn frame_time(&self) -> f32 {
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32
+    }
+
+    // Records reading back the live boid count, which only the GPU knows.
+    pub fn copy_population(&mut self, encoder: &mut wgpu::CommandEncoder, population_buffer: &wgpu::Buffer) {
+        self.population_readback.copy(encoder, population_buffer);
+    }
+
+    // Call once the commands from `copy_population` have been submitted.
+    pub fn map(&mut self) {
+        self.population_readback.map();
+    }
+
+    pub fn read(&mut self) {
+        if let Some(population) = self.population_readback.read::<u32>() {
+            self.population = population.first().copied();
+        }
+    }
+
+    pub fn show(&self, gui: &mut Gui, profiler: Option<&Profiler>, analytics: Option<&Analytics>) {
+        gui.begin_panel(Corner::TopRight);
+        gui.heading("performance");
+        let frame_time = self.frame_time();
+        gui.label(&format!("{:.1} fps {frame_time:.2} ms", 1000.0 / frame_time.max(0.001)));
+        let max = self.frame_times.iter().copied().fold(GRAPH_MIN, f32::max);
+        gui.graph(self.frame_times.iter().copied(), HISTORY, max);
+        gui.label(&format!("{:<13}{}", "boids", self.population.map_or("?".to_string(), |p| p.to_string())));
+        gui.label(&format!("{:<13}{:.1}", "steps/s", self.steps_per_second));
+
+        gui.heading("gpu");
+        match profiler {
+            Some(profiler) => for pass in Pass::ALL {
+                let timing = profiler.timing(pass).map_or("-".to_string(), |ms| format!("{ms:.3} ms"));
+                gui.label(&format!("{:<13}{timing}", pass.name()));
+            }
+            None => gui.label("no timestamp queries"),
+        }
+
+        if let Some(analytics) = analytics {
+            gui.heading("flock");
+            let history = analytics.history();
+            let latest = history.back().map(|(_, metrics)| *metrics).unwrap_or_default();
+            gui.label(&format!("{:<13}{:.3}", "polarization", latest.polarization));
+            gui.graph(history.iter().map(|(_, m)| m.polarization), analytics::HISTORY, 1.0);
+            gui.label(&format!("{:<13}{:.3}", "milling", latest.milling));
+            gui.graph(history.iter().map(|(_, m)| m.milling), analytics::HISTORY, 1.0);
+            gui.label(&format!("{:<13}{:.2}", "nearest", latest.nearest_neighbour));
+            gui.label(&format!("{:<13}{:.1}", "gyration", latest.radius_of_gyration));
+            gui.label(&format!("{:<13}{}", "clusters", latest.clusters));
+        }
+        gui.end_panel();
+    }
+}
@@ -0,0 +1,367 @@
+use rand::prelude::*;
+use serde::Deserialize;
+
+use crate::boid::{self, Boid, BoidTraits};
+use crate::image::Image;
+use crate::spawner::{self, EXTENT};
+
+// Pixels darker than this, out of 1, are where boids are spawned from an image.
+const DARK: f32 = 0.5;
+
+// A boid as it's given in a file. Whatever's left out is drawn at random, the way a
+// `Spawner` would.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Record {
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    vx: Option<f32>,
+    #[serde(default)]
+    vy: Option<f32>,
+    // an id in `boid::SPECIES`
+    #[serde(default)]
+    species: Option<u32>,
+}
+
+// Loads the boids to start a run with from `path`, going by its extension:
+//
+//     .csv   a header naming `x`, `y` and optionally `vx`, `vy` and `species` columns,
+//            in any order, then a row per boid; any other columns are ignored
+//     .json  an array of objects with those fields
+//     .npy   an array of x and y, or x, y, vx and vy, per boid, as (boids, 2) or
+//            (boids, 4), or the first frame of a (frames, boids, 4) export
+//     .png   `count` boids at random dark pixels, with the image fitted to the square
+//            the spawners fill
+pub fn load(path: &str, rng: &mut impl Rng, count: usize) -> anyhow::Result<(Vec<Boid>, Vec<BoidTraits>)> {
+    let extension = std::path::Path::new(path).extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let records = match extension.as_deref() {
+        Some("csv") => read_csv(&std::fs::read_to_string(path)?)?,
+        Some("json") => serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(path)?))?,
+        Some("npy") => read_npy(&std::fs::read(path)?)?,
+        Some("png") => read_image(&Image::load_png(path)?, rng, count)?,
+        _ => anyhow::bail!("expected a .csv, .json, .npy or .png file"),
+    };
+    anyhow::ensure!(!records.is_empty(), "there are no boids in it");
+
+    let mut boids = Vec::with_capacity(records.len());
+    let mut traits = Vec::with_capacity(records.len());
+    for (i, record) in records.into_iter().enumerate() {
+        let (boid, boid_traits) = build(record, rng).map_err(|err| anyhow::anyhow!("boid {i}: {err}"))?;
+        boids.push(boid);
+        traits.push(boid_traits);
+    }
+    Ok((boids, traits))
+}
+
+fn build(record: Record, rng: &mut impl Rng) -> anyhow::Result<(Boid, BoidTraits)> {
+    let Record { x, y, vx, vy, species } = record;
+    let [vx, vy] = match (vx, vy) {
+        (Some(vx), Some(vy)) => [vx, vy],
+        (None, None) => spawner::random_heading(rng),
+        _ => anyhow::bail!("it has only one of vx and vy"),
+    };
+    anyhow::ensure!(
+        [x, y, vx, vy].iter().all(|v| v.is_finite()),
+        "its position and velocity have to be finite, not {:?}",
+        [x, y, vx, vy],
+    );
+    let traits = match species {
+        Some(species) => *boid::SPECIES.iter().find(|t| t.species() == species).ok_or_else(|| {
+            anyhow::anyhow!("there's no species {species}, only 0 to {}", boid::species_count() - 1)
+        })?,
+        None => spawner::random_species(rng),
+    };
+    Ok((Boid::new(x, y, vx, vy), traits))
+}
+
+fn read_csv(text: &str) -> anyhow::Result<Vec<Record>> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or_else(|| anyhow::anyhow!("the file is empty"))?;
+    let names: Vec<String> = header.split(',').map(|name| name.trim().to_ascii_lowercase()).collect();
+    let column = |name: &str| names.iter().position(|n| n == name);
+    let [x, y] = ["x", "y"].map(|name| column(name).ok_or_else(|| anyhow::anyhow!("there's no {name} column")));
+    let (x, y) = (x?, y?);
+    let (vx, vy, species) = (column("vx"), column("vy"), column("species"));
+
+    let mut records = Vec::new();
+    for (i, line) in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |column: usize| -> anyhow::Result<&str> {
+            fields.get(column).copied().ok_or_else(|| {
+                anyhow::anyhow!("line {}: expected {} fields, not {}", i + 1, names.len(), fields.len())
+            })
+        };
+        let number = |column: usize| -> anyhow::Result<f32> {
+            let field = field(column)?;
+            field.parse().map_err(|_| anyhow::anyhow!("line {}: {field:?} isn't a number", i + 1))
+        };
+        records.push(Record {
+            x: number(x)?,
+            y: number(y)?,
+            vx: vx.map(number).transpose()?,
+            vy: vy.map(number).transpose()?,
+            species: species.map(|column| -> anyhow::Result<u32> {
+                let field = field(column)?;
+                field.parse().map_err(|_| anyhow::anyhow!("line {}: {field:?} isn't a species id", i + 1))
+            }).transpose()?,
+        });
+    }
+    Ok(records)
+}
+
+// The value of `key` in a `.npy` header, which is a Python dict literal.
+fn npy_value<'a>(header: &'a str, key: &str) -> anyhow::Result<&'a str> {
+    let missing = || anyhow::anyhow!("the header has no {key}");
+    let start = header.find(&format!("'{key}'")).ok_or_else(missing)?;
+    let value = header[start + key.len() + 2..].trim_start().strip_prefix(':').ok_or_else(missing)?.trim_start();
+    let end = match value.chars().next() {
+        Some('(') => value.find(')').map(|end| end + 1),
+        Some(quote @ ('\'' | '"')) => value[1..].find(quote).map(|end| end + 2),
+        _ => value.find([',', '}']),
+    };
+    Ok(&value[..end.ok_or_else(missing)?])
+}
+
+fn read_npy(bytes: &[u8]) -> anyhow::Result<Vec<Record>> {
+    anyhow::ensure!(bytes.starts_with(b"\x93NUMPY") && bytes.len() >= 10, "not a .npy file");
+    let (len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize, 12),
+        version => anyhow::bail!("unsupported .npy version {version}"),
+    };
+    let header = bytes.get(start..start + len).ok_or_else(|| anyhow::anyhow!("the .npy is truncated"))?;
+    let header = std::str::from_utf8(header)?;
+
+    anyhow::ensure!(npy_value(header, "fortran_order")? == "False", "only C ordered arrays are supported");
+    let descr = npy_value(header, "descr")?.trim_matches(['\'', '"']);
+    let shape = npy_value(header, "shape")?.trim_matches(['(', ')']);
+    let shape = shape.split(',')
+        .map(str::trim)
+        .filter(|len| !len.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<usize>, _>>()?;
+
+    let data = &bytes[start + len..];
+    let values: Vec<f32> = match descr {
+        "<f4" => data.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect(),
+        ">f4" => data.chunks_exact(4).map(|b| f32::from_be_bytes(b.try_into().unwrap())).collect(),
+        "<f8" => data.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap()) as f32).collect(),
+        ">f8" => data.chunks_exact(8).map(|b| f64::from_be_bytes(b.try_into().unwrap()) as f32).collect(),
+        _ => anyhow::bail!("expected an array of 32 or 64 bit floats, not {descr:?}"),
+    };
+    let (rows, width) = match shape[..] {
+        [rows, width @ (2 | 4)] => (rows, width),
+        // the first frame of an export, which is padded with NaN after the live boids
+        [_, rows, 4] => (rows, 4),
+        _ => anyhow::bail!("expected an array of (boids, 2), (boids, 4) or (frames, boids, 4), not {shape:?}"),
+    };
+    anyhow::ensure!(values.len() >= rows * width, "the .npy is truncated");
+
+    Ok(values[..rows * width].chunks_exact(width)
+        .take_while(|row| !row.iter().all(|v| v.is_nan()))
+        .map(|row| Record {
+            x: row[0],
+            y: row[1],
+            vx: row.get(2).copied(),
+            vy: row.get(3).copied(),
+            species: None,
+        })
+        .collect())
+}
+
+fn read_image(image: &Image, rng: &mut impl Rng, count: usize) -> anyhow::Result<Vec<Record>> {
+    let mut dark = Vec::new();
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let [r, g, b, a] = image.pixel(x, y).map(|c| c as f32 / 255.0);
+            if a >= 0.5 && 0.2126 * r + 0.7152 * g + 0.0722 * b < DARK {
+                dark.push([x, y]);
+            }
+        }
+    }
+    anyhow::ensure!(!dark.is_empty(), "the image has no dark pixels to spawn boids at");
+
+    let scale = 2.0 * EXTENT / image.width.max(image.height) as f32;
+    let centre = [image.width as f32 / 2.0, image.height as f32 / 2.0];
+    Ok((0..count)
+        .map(|_| {
+            let [x, y] = *dark.choose(rng).unwrap();
+            Record {
+                x: (x as f32 + rng.random::<f32>() - centre[0]) * scale,
+                y: (y as f32 + rng.random::<f32>() - centre[1]) * scale,
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{components, TempPath};
+
+    fn rng() -> rand::rngs::StdRng {
+        rand::rngs::StdRng::seed_from_u64(1)
+    }
+
+    fn error(result: anyhow::Result<impl std::fmt::Debug>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    // A `.npy` of `values` as little endian floats of `kind`, e.g. "<f4".
+    fn npy(kind: &str, shape: &str, values: &[f64]) -> Vec<u8> {
+        let dict = format!("{{'descr': '{kind}', 'fortran_order': False, 'shape': {shape}, }}\n");
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((dict.len() as u16).to_le_bytes());
+        bytes.extend(dict.as_bytes());
+        for &value in values {
+            match kind {
+                "<f4" => bytes.extend((value as f32).to_le_bytes()),
+                ">f4" => bytes.extend((value as f32).to_be_bytes()),
+                "<f8" => bytes.extend(value.to_le_bytes()),
+                _ => bytes.extend(value.to_be_bytes()),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn csv_columns_go_by_name() {
+        let records = read_csv("Species, VY,x,note,y,vx\n1,0.5,10,a,-20,0.25\n\n0,1,3,b,4,0\n").unwrap();
+        assert_eq!(records.len(), 2);
+        let Record { x, y, vx, vy, species } = records[0];
+        assert_eq!((x, y, vx, vy, species), (10.0, -20.0, Some(0.25), Some(0.5), Some(1)));
+        assert_eq!(records[1].species, Some(0));
+
+        let records = read_csv("y,x\n1,2\n").unwrap();
+        let Record { x, y, vx, vy, species } = records[0];
+        assert_eq!((x, y, vx, vy, species), (2.0, 1.0, None, None, None));
+    }
+
+    #[test]
+    fn bad_csvs_are_rejected() {
+        assert_eq!(error(read_csv("")), "the file is empty");
+        assert_eq!(error(read_csv("x,vx\n1,2\n")), "there's no y column");
+        assert_eq!(error(read_csv("x,y\n1,2\n3\n")), "line 3: expected 2 fields, not 1");
+        assert_eq!(error(read_csv("x,y\n1,two\n")), "line 2: \"two\" isn't a number");
+        assert_eq!(error(read_csv("x,y,species\n1,2,-1\n")), "line 2: \"-1\" isn't a species id");
+    }
+
+    #[test]
+    fn records_are_validated() {
+        let record = |vx, vy, species| Record { x: 1.0, y: 2.0, vx, vy, species };
+        let (boid, traits) = build(record(Some(0.5), Some(-0.5), Some(1)), &mut rng()).unwrap();
+        assert_eq!(components(&boid), [1.0, 2.0, 0.5, -0.5]);
+        assert_eq!(traits.species(), 1);
+
+        // a random heading at unit speed
+        let (boid, _) = build(record(None, None, None), &mut rng()).unwrap();
+        let [_, _, vx, vy] = components(&boid);
+        assert!((vx.hypot(vy) - 1.0).abs() < 1e-6);
+
+        assert_eq!(error(build(record(Some(1.0), None, None), &mut rng())), "it has only one of vx and vy");
+        assert!(error(build(record(Some(f32::NAN), Some(0.0), None), &mut rng())).contains("have to be finite"));
+        assert_eq!(
+            error(build(record(None, None, Some(9)), &mut rng())),
+            format!("there's no species 9, only 0 to {}", boid::species_count() - 1),
+        );
+    }
+
+    #[test]
+    fn npy_values_are_found_by_key() {
+        let header = "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 4), }";
+        assert_eq!(npy_value(header, "descr").unwrap(), "'<f4'");
+        assert_eq!(npy_value(header, "fortran_order").unwrap(), "False");
+        assert_eq!(npy_value(header, "shape").unwrap(), "(3, 4)");
+        assert_eq!(npy_value("{'shape':(5,)}", "shape").unwrap(), "(5,)");
+        assert_eq!(error(npy_value(header, "dtype")), "the header has no dtype");
+        assert_eq!(error(npy_value("{'shape': (3, 4", "shape")), "the header has no shape");
+    }
+
+    #[test]
+    fn npy_arrays_of_any_float() {
+        let values = [1.0, 2.0, 0.5, -0.5, 3.0, 4.0, 0.0, 1.0];
+        for kind in ["<f4", ">f4", "<f8", ">f8"] {
+            let records = read_npy(&npy(kind, "(2, 4)", &values)).unwrap();
+            assert_eq!(records.len(), 2);
+            assert_eq!((records[1].x, records[1].y, records[1].vx, records[1].vy), (3.0, 4.0, Some(0.0), Some(1.0)));
+        }
+        let records = read_npy(&npy("<f4", "(4, 2)", &values)).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!((records[3].x, records[3].y, records[3].vx), (0.0, 1.0, None));
+    }
+
+    #[test]
+    fn npy_exports_give_their_first_frame() {
+        // two frames of up to three boids, with only two alive in the first
+        let nan = f64::NAN;
+        let values = [
+            1.0, 2.0, 0.0, 1.0, 3.0, 4.0, 1.0, 0.0, nan, nan, nan, nan,
+            5.0, 6.0, 0.0, 1.0, 7.0, 8.0, 1.0, 0.0, 9.0, 9.0, 0.0, 1.0,
+        ];
+        let records = read_npy(&npy("<f4", "(2, 3, 4)", &values)).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[1].x, records[1].y), (3.0, 4.0));
+    }
+
+    #[test]
+    fn bad_npys_are_rejected() {
+        assert_eq!(error(read_npy(b"PK\x03\x04")), "not a .npy file");
+        assert_eq!(error(read_npy(&npy("<i4", "(2, 2)", &[0.0; 4]))), "expected an array of 32 or 64 bit floats, not \"<i4\"");
+        assert!(error(read_npy(&npy("<f4", "(2, 3)", &[0.0; 6]))).starts_with("expected an array of (boids, 2)"));
+        assert_eq!(error(read_npy(&npy("<f4", "(2, 2)", &[0.0; 3]))), "the .npy is truncated");
+
+        let mut fortran = npy("<f4", "(2, 2)", &[0.0; 4]);
+        let at = fortran.windows(5).position(|w| w == b"False").unwrap();
+        fortran[at..at + 5].copy_from_slice(b"True ");
+        assert_eq!(error(read_npy(&fortran)), "only C ordered arrays are supported");
+
+        let mut truncated = npy("<f4", "(2, 2)", &[0.0; 4]);
+        truncated.truncate(20);
+        assert_eq!(error(read_npy(&truncated)), "the .npy is truncated");
+    }
+
+    #[test]
+    fn images_spawn_boids_on_dark_pixels() {
+        // a 4x2 image, white but for one black pixel and one that's see through
+        let mut pixels = vec![[255; 4]; 8];
+        pixels[5] = [0, 0, 0, 255];
+        pixels[6] = [0, 0, 0, 0];
+        let image = Image { width: 4, height: 2, pixels };
+        let records = read_image(&image, &mut rng(), 100).unwrap();
+        assert_eq!(records.len(), 100);
+        // the second pixel of the bottom row, since rows are stored top down and y goes up,
+        // of a 4 pixel wide image fitted to the spawn square
+        let scale = 2.0 * EXTENT / 4.0;
+        for record in records {
+            assert!((-scale..0.0).contains(&record.x), "{}", record.x);
+            assert!((-scale..0.0).contains(&record.y), "{}", record.y);
+        }
+
+        let white = Image { width: 2, height: 2, pixels: vec![[255; 4]; 4] };
+        assert_eq!(error(read_image(&white, &mut rng(), 10)), "the image has no dark pixels to spawn boids at");
+    }
+
+    #[test]
+    fn files_are_read_by_extension() {
+        let dir = TempPath::new("import");
+        let json = dir.write("boids.json", r#"[{ "x": 1, "y": 2, "vx": 0, "vy": 1, "species": 1 }, { "x": -1, "y": 0 }]"#);
+        let (boids, traits) = load(&json, &mut rng(), 0).unwrap();
+        assert_eq!(boids.len(), 2);
+        assert_eq!(components(&boids[0]), [1.0, 2.0, 0.0, 1.0]);
+        assert_eq!(traits[0].species(), 1);
+
+        let csv = dir.write("boids.CSV", "x,y,species\n1,2,7\n");
+        assert_eq!(error(load(&csv, &mut rng(), 0)), "boid 0: there's no species 7, only 0 to 1");
+
+        let unknown = dir.write("unknown.json", r#"[{ "x": 1, "y": 2, "z": 3 }]"#);
+        assert!(error(load(&unknown, &mut rng(), 0)).contains("unknown field `z`"));
+        let empty = dir.write("empty.json", "[]");
+        assert_eq!(error(load(&empty, &mut rng(), 0)), "there are no boids in it");
+        let text = dir.write("boids.txt", "x,y\n1,2\n");
+        assert_eq!(error(load(&text, &mut rng(), 0)), "expected a .csv, .json, .npy or .png file");
+    }
+}
@@ -1,5 +1,39 @@
 mod camera;
 mod boid;
+mod params;
+mod options;
+mod orbit_camera;
+mod sim3d;
+mod scenario;
+mod overlay;
+mod image;
+mod flow_field;
+mod coloring;
+mod trails;
+mod fullscreen;
+mod heatmap;
+mod msaa;
+mod style;
+mod shape;
+mod culling;
+mod lod;
+mod spawning;
+mod spawner;
+mod gui;
+mod readback;
+mod profiler;
+mod hud;
+mod simulation;
+mod bench;
+mod analytics;
+mod sweep;
+mod trajectory;
+mod video;
+mod export;
+mod import;
+mod error;
+#[cfg(test)]
+mod test_util;
 
 use winit::{
     event::*,
@@ -8,12 +42,37 @@ use winit::{
     window::{WindowBuilder, Window},
 };
 
+use std::sync::{Arc, Mutex};
+
 use wgpu::util::DeviceExt;
 
 use rand::prelude::*;
 
 use camera::{Camera, CameraUniform};
-use boid::Boid;
+use boid::{Boid, BoidTraits, BoidStats, InteractionMatrix};
+use params::{SimParams, Integrator};
+use options::Options;
+use sim3d::Sim3D;
+use scenario::Scenario;
+use overlay::Overlay;
+use coloring::{ColorMode, ColoringUniform};
+use trails::{Trails, TrailMode};
+use heatmap::{Heatmap, HeatmapMode};
+use msaa::Multisample;
+use style::BoidStyle;
+use shape::BoidShape;
+use image::Image;
+use culling::Culling;
+use lod::Lod;
+use spawner::Spawner;
+use gui::{Corner, Gui};
+use profiler::{Pass, Profiler};
+use hud::Hud;
+use simulation::{Simulation, NeighbourSearch};
+use analytics::Analytics;
+use trajectory::{Encoding, Recorder, Replay};
+use video::Video;
+use error::Error;
 
 struct Renderer<'a> {
     surface: wgpu::Surface<'a>,
@@ -21,44 +80,111 @@ struct Renderer<'a> {
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    // why the device was lost, if it has been; checked after every frame
+    device_lost: Arc<Mutex<Option<String>>>,
 
     frame_count: usize,
-
-    boids_buffers: Vec<wgpu::Buffer>,
-    boids_bind_groups: Vec<wgpu::BindGroup>,
+    // whether the boids have taken a step since the last frame was drawn
+    stepped: bool,
+    paused: bool,
+    step_requested: bool,
+
+    seed: u64,
+    spawner: Spawner,
+    // the boids loaded with `--import`, used in place of the spawner's until it's changed
+    imported: Option<(Vec<Boid>, Vec<BoidTraits>)>,
+    simulation: Simulation,
+    params: SimParams,
+
+    scenario: Scenario,
+    cursor: [f32; 2],
+    overlay: Overlay,
+    show_flow: bool,
     
     camera: Camera,
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
 
+    coloring: ColoringUniform,
+    coloring_buffer: wgpu::Buffer,
+
+    trails: Trails,
+    heatmap: Heatmap,
+
     staging_buffer: wgpu::util::StagingBelt,
 
-    vertex_buffer: wgpu::Buffer,
+    shape: BoidShape,
+    mesh_buffer: wgpu::Buffer,
+    mesh_vertex_count: u32,
+    sprite_vertex_buffer: wgpu::Buffer,
 
+    msaa: Multisample,
+    style: BoidStyle,
     render_pipeline: wgpu::RenderPipeline,
-    compute_pipeline: wgpu::ComputePipeline,
+    sprite_pipeline: wgpu::RenderPipeline,
+    // the pipeline and image for `BoidStyle::Texture`, when `--sprite` was given
+    textured_sprite: Option<(wgpu::RenderPipeline, wgpu::BindGroup)>,
+    point_pipeline: wgpu::RenderPipeline,
+    culling: Culling,
+    lod_enabled: bool,
+
+    sim3d: Option<Sim3D>,
+
+    gui: Gui,
+    show_gui: bool,
+    hud: Hud,
+    show_hud: bool,
+    // measures the flock after every step, when asked for with `--analytics`
+    analytics: Option<Analytics>,
+    // times passes on the GPU, when the adapter supports timestamp queries
+    profiler: Option<Profiler>,
+    // writes the boids to a trajectory file, when asked for with `--trajectory`
+    recorder: Option<Recorder>,
+    // plays a trajectory file back instead of simulating, with `--replay`
+    replay: Option<Replay>,
+    // where the scene is drawn instead of the window, when recording with `--record`
+    video: Option<Video>,
 
     window: &'a Window,
 }
 
-const VERTICES: &[[f32; 3]] = &[
-    [ 1.00000,  0.00000, 1.0],
-    [-0.83147,  0.55557,  1.0],
-    [-0.83147, -0.55557,  1.0],
+// Corners of the quad each sprite is drawn on.
+const SPRITE_VERTICES: &[[f32; 3]] = &[
+    [-1.0, -1.0, 1.0],
+    [ 1.0, -1.0, 1.0],
+    [ 1.0,  1.0, 1.0],
+    [-1.0, -1.0, 1.0],
+    [ 1.0,  1.0, 1.0],
+    [-1.0,  1.0, 1.0],
 ];
 
 const N_BOIDS: usize = 10000;
 
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.009021491898012131,
+    g: 0.009021491898012131,
+    b: 0.023103556157921437,
+    a: 1.0,
+};
+
+// The size and frame rate of videos recorded with `--record`, unless given.
+const VIDEO_SIZE: [u32; 2] = [1920, 1080];
+const VIDEO_FPS: u32 = 30;
+
+// Arrows per side when drawing the flow field.
+const FLOW_ARROWS: u32 = 48;
+
 impl<'a> Renderer<'a> {
-    async fn new(window: &'a Window) -> Renderer<'a> {
+    async fn new(window: &'a Window, options: &Options, scenario: Scenario) -> anyhow::Result<Renderer<'a>> {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY),
             ..Default::default()
         });
 
-        let surface = instance.create_surface(window).unwrap();
+        let surface = instance.create_surface(window)
+            .map_err(|err| Error::Surface(err.to_string()))?;
 
         let adapter = instance.request_adapter(
             &wgpu::RequestAdapterOptions {
@@ -66,119 +192,107 @@ impl<'a> Renderer<'a> {
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
             }
-        ).await.unwrap();
+        ).await.ok_or(Error::NoAdapter)?;
 
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
-                required_features: wgpu::Features::empty(),
+                // the first is only needed for MSAA sample counts other than 4, and the
+                // second for GPU timings in the HUD
+                required_features: adapter.features() & (
+                    wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                    | wgpu::Features::TIMESTAMP_QUERY
+                ),
                 required_limits: wgpu::Limits::default(),
                 label: None,
                 memory_hints: Default::default(),
             },
             None,
-        ).await.unwrap();
+        ).await.map_err(|err| Error::RequestDevice(err.to_string()))?;
+
+        let device_lost = Arc::new(Mutex::new(None));
+        let lost = device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // it's also called when the device is dropped on the way out
+            if matches!(reason, wgpu::DeviceLostReason::Unknown | wgpu::DeviceLostReason::DeviceInvalid) {
+                *lost.lock().unwrap() = Some(message);
+            }
+        });
+        // the shaders are only checked as the pipelines are made, and anything that
+        // fails would otherwise panic in wgpu's default error handler
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
 
         let surface_caps = surface.get_capabilities(&adapter);
 
         let surface_format = surface_caps.formats.iter()
             .find(|r| r.is_srgb())
+            .or(surface_caps.formats.first())
             .copied()
-            .unwrap_or(surface_caps.formats[0]);
+            .ok_or(Error::NoSurfaceFormat)?;
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
+            // Fifo is the one mode every surface has to support, and Auto lets wgpu pick
+            present_mode: surface_caps.present_modes.first().copied().unwrap_or(wgpu::PresentMode::Fifo),
+            alpha_mode: surface_caps.alpha_modes.first().copied().unwrap_or(wgpu::CompositeAlphaMode::Auto),
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
 
+        let video = options.record.as_deref().map(|path| Video::start(
+            &device,
+            config.format,
+            path,
+            options.record_size.unwrap_or(VIDEO_SIZE),
+            options.record_fps.unwrap_or(VIDEO_FPS),
+        ).map_err(|err| anyhow::anyhow!("failed to record {path:?}: {err}"))).transpose()?;
+        let scene_config = scene_config(&config, video.as_ref());
 
-        let frame_count = 0;
-
+        let msaa = Multisample::new(&adapter, &device, &scene_config, options.msaa.unwrap_or(1));
 
-        let wd = 1024.0;
-        let ht = 1024.0;
-        let mut rng = rand::rng();
-        let mut boids = Vec::new();
-        for _ in 0..N_BOIDS {
-            let x = wd * rng.random::<f32>() - (wd / 2.0);
-            let y = ht * rng.random::<f32>() - (ht / 2.0);
-            let a = rng.random::<f32>() * 6.28318;
-            let (vy, vx) = f32::sin_cos(a);
-            let boid = Boid::new(x, y, vx, vy);
-            boids.push(boid);
-        }
-        
-
-        let mut boids_buffers = Vec::new();
-        for i in 0..2 {
-            let buffer = device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor {
-                    label: Some(format!("Boids Buffer {}", i).as_str()),
-                    contents: bytemuck::cast_slice(&boids),
-                    usage: wgpu::BufferUsages::VERTEX
-                        | wgpu::BufferUsages::STORAGE
-                        | wgpu::BufferUsages::COPY_DST,
-                }
-            );
-            boids_buffers.push(buffer);
-        }
 
-        let boids_bind_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                label: Some("Boid Bind Group Layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }
-                ],
-            }
-        );
+        let frame_count = 0;
 
-        let mut boids_bind_groups = Vec::new();
-        for i in 0..2 {
-            let bind_group = device.create_bind_group(
-                &wgpu::BindGroupDescriptor {
-                    label: Some(format!("Bind Group {}", i).as_str()),
-                    layout: &boids_bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: boids_buffers[i % 2].as_entire_binding(),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: boids_buffers[(i + 1) % 2].as_entire_binding(),
-                        },
-                    ],
-                }
-            );
-            boids_bind_groups.push(bind_group);
-        }
 
+        // everything random, on the CPU and the GPU, derives from this seed
+        let seed = options.seed.unwrap_or_else(rand::random);
+        log::info!("seed: {seed}");
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let spawner = options.spawner.unwrap_or(Spawner::Square);
+        let mut replay = options.replay.as_deref().map(|path| Replay::load(path)
+            .map_err(|err| anyhow::anyhow!("failed to load trajectory {path:?}: {err}"))).transpose()?;
+        let imported = options.import.as_deref().map(|path| -> anyhow::Result<_> {
+            let imported = import::load(path, &mut rng, N_BOIDS)
+                .map_err(|err| anyhow::anyhow!("failed to import boids from {path:?}: {err}"))?;
+            log::info!("imported {} boids from {path:?}", imported.0.len());
+            Ok(imported)
+        }).transpose()?;
+        let (boids, traits) = match (&mut replay, &imported) {
+            (Some(replay), _) => {
+                log::info!("replaying {} frames of {} boids", replay.frame_count(), replay.capacity());
+                replay.initial_boids()
+            }
+            (None, Some(imported)) => imported.clone(),
+            (None, None) => spawner.spawn(&mut rng, N_BOIDS),
+        };
 
-        let camera = Camera::new(size);
+        let interactions = scenario.interactions();
+        let params = sim_params(options, &scenario, &interactions, seed);
+        let simulation = Simulation::new(
+            &device,
+            &queue,
+            &scenario,
+            params,
+            &interactions,
+            &boids,
+            &traits,
+            seed,
+            options.search.unwrap_or(NeighbourSearch::BruteForce),
+        )?;
+
+        let camera = Camera::new(winit::dpi::PhysicalSize::new(scene_config.width, scene_config.height));
         let camera_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Camera Buffer"),
@@ -187,6 +301,15 @@ impl<'a> Renderer<'a> {
             }
         );
 
+        let coloring = ColoringUniform::new(options.color_mode.unwrap_or(ColorMode::Species), options.color_ramp);
+        let coloring_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Coloring Buffer"),
+                contents: bytemuck::cast_slice(&[coloring]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
         let camera_bind_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
                 label: Some("Camera Bind Group Layout"),
@@ -200,7 +323,17 @@ impl<'a> Renderer<'a> {
                             min_binding_size: None,
                         },
                         count: None,
-                    }
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ]
             }
         );
@@ -212,7 +345,11 @@ impl<'a> Renderer<'a> {
                     wgpu::BindGroupEntry {
                         binding: 0,
                         resource: camera_buffer.as_entire_binding(),
-                    }
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: coloring_buffer.as_entire_binding(),
+                    },
                 ]
             }
         );
@@ -231,122 +368,314 @@ impl<'a> Renderer<'a> {
             }
         );
 
-        let render_shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
-
-        let render_pipeline = device.create_render_pipeline(
-            &wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &render_shader,
-                    entry_point: "vs_main",
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    buffers: &[
-                        wgpu::VertexBufferLayout {
-                            array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+        let render_shader = device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("shader.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(include_str!("ramps.wgsl"), include_str!("shader.wgsl")).into()),
+            }
+        );
+
+        let render_pipeline = create_boid_pipeline(
+            &device,
+            "Render Pipeline",
+            &pipeline_layout,
+            &render_shader,
+            ("vs_main", "fs_main"),
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            msaa.state(),
+        );
+        let sprite_pipeline = create_boid_pipeline(
+            &device,
+            "Sprite Pipeline",
+            &pipeline_layout,
+            &render_shader,
+            ("vs_sprite", "fs_sprite"),
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            msaa.state(),
+        );
+        let point_pipeline = create_boid_pipeline(
+            &device,
+            "Point Pipeline",
+            &pipeline_layout,
+            &render_shader,
+            ("vs_point", "fs_main"),
+            wgpu::PrimitiveTopology::PointList,
+            wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            msaa.state(),
+        );
+
+        let textured_sprite = options.sprite.as_deref().map(|path| -> anyhow::Result<_> {
+            let image = Image::load_png(path)
+                .map_err(|err| anyhow::anyhow!("failed to load sprite {path:?}: {err}"))?;
+            let view = image.create_texture(&device, &queue, "Sprite Texture")
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            let sampler = device.create_sampler(
+                &wgpu::SamplerDescriptor {
+                    label: Some("Sprite Sampler"),
+                    mag_filter: wgpu::FilterMode::Linear,
+                    min_filter: wgpu::FilterMode::Linear,
+                    ..Default::default()
+                }
+            );
+
+            let sprite_bind_group_layout = device.create_bind_group_layout(
+                &wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Sprite Bind Group Layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
                         },
-                        wgpu::VertexBufferLayout {
-                            array_stride: std::mem::size_of::<Boid>() as wgpu::BufferAddress,
-                            step_mode: wgpu::VertexStepMode::Instance,
-                            attributes: &wgpu::vertex_attr_array![1 => Float32x2, 2 => Float32x2],
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
                         },
                     ],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &render_shader,
-                    entry_point: "fs_main",
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    targets: &[
-                        Some(wgpu::ColorTargetState {
-                            format: config.format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        }),
+                }
+            );
+            let sprite_bind_group = device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    label: Some("Sprite Bind Group"),
+                    layout: &sprite_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
                     ],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
-            }
-        );
+                }
+            );
 
-        let compute_shader = device.create_shader_module(wgpu::include_wgsl!("compute.wgsl"));
+            let texture_pipeline_layout = device.create_pipeline_layout(
+                &wgpu::PipelineLayoutDescriptor {
+                    label: Some("Texture Pipeline Layout"),
+                    bind_group_layouts: &[
+                        &camera_bind_group_layout,
+                        &sprite_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                }
+            );
+            let texture_pipeline = create_boid_pipeline(
+                &device,
+                "Texture Pipeline",
+                &texture_pipeline_layout,
+                &render_shader,
+                ("vs_sprite", "fs_texture"),
+                wgpu::PrimitiveTopology::TriangleList,
+                wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                },
+                msaa.state(),
+            );
+            Ok((texture_pipeline, sprite_bind_group))
+        }).transpose()?;
+        let mut style = options.style.unwrap_or(BoidStyle::Triangles);
+        if style == BoidStyle::Texture && textured_sprite.is_none() {
+            log::warn!("the texture style needs a --sprite image, using sprites");
+            style = BoidStyle::Sprites;
+        }
 
-        let compute_pipeline_layout = device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute Pipeline Layout"),
-                bind_group_layouts: &[
-                    &boids_bind_group_layout
-                ],
-                push_constant_ranges: &[],
+        let shape = options.shape.unwrap_or(BoidShape::Triangle);
+        let mesh = match &options.mesh {
+            Some(path) => shape::load_mesh(path)
+                .map_err(|err| anyhow::anyhow!("failed to load mesh {path:?}: {err}"))?,
+            None => shape.vertices(),
+        };
+        let mesh_buffer = create_mesh_buffer(&device, &mesh);
+        let sprite_vertex_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Sprite Vertex Buffer"),
+                contents: bytemuck::cast_slice(SPRITE_VERTICES),
+                usage: wgpu::BufferUsages::VERTEX,
             }
         );
 
-        let compute_pipeline = device.create_compute_pipeline(
-            &wgpu::ComputePipelineDescriptor {
-                label: Some("Compute Pipeline"),
-                layout: Some(&compute_pipeline_layout),
-                module: &compute_shader,
-                entry_point: "cs_main",
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                cache: None,
-            }
+        let culling = Culling::new(
+            &device,
+            &simulation.boids_buffers,
+            &simulation.traits_buffer,
+            &simulation.stats_buffer,
+            &camera_buffer,
+            &simulation.population_buffer,
         );
 
+        let overlay = Overlay::new(&device, config.format, &camera_bind_group_layout);
 
-        let vertex_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(VERTICES),
-                usage: wgpu::BufferUsages::VERTEX,
+        let mut trails = Trails::new(&device, &scene_config, msaa.state(), &simulation.boids_buffers, &camera_bind_group_layout, CLEAR_COLOR);
+        trails.set_mode(options.trails.unwrap_or(TrailMode::Off));
+
+        let mut heatmap = Heatmap::new(&device, &scene_config, msaa.state(), &camera_bind_group_layout, coloring.ramp());
+        heatmap.set_mode(options.heatmap.unwrap_or(HeatmapMode::Off));
+
+        if options.three_d && replay.is_some() {
+            log::warn!("trajectories are only replayed in 2D");
+        }
+        if options.three_d && replay.is_none() {
+            // the goals, flow field and population are shared with 3D, but these only
+            // know about the 2D boids
+            if options.trails.is_some_and(|mode| mode != TrailMode::Off) {
+                log::warn!("trails are only drawn in 2D");
             }
-        );
+            if options.heatmap.is_some_and(|mode| mode != HeatmapMode::Off) {
+                log::warn!("the heatmap is only drawn in 2D");
+            }
+            if !scenario.emitters.is_empty() || !scenario.sinks.is_empty() {
+                log::warn!("emitters and sinks only run in 2D");
+            }
+        }
+        let sim3d = (options.three_d && replay.is_none()).then(|| Sim3D::new(
+            &device,
+            &scene_config,
+            &mut rng,
+            &simulation,
+            params,
+        ));
+
+        let gui = Gui::new(&device, &queue, config.format);
+        window.set_cursor_visible(options.gui);
+        let hud = Hud::new(&device);
+        let profiler = Profiler::new(&device, &queue);
+        let analytics = match options.analytics && options.three_d {
+            true => {
+                log::warn!("flock analytics are only measured in 2D");
+                None
+            }
+            false => options.analytics.then(|| -> anyhow::Result<_> {
+                let mut analytics = Analytics::new(&device, &simulation);
+                if let Some(path) = &options.analytics_csv {
+                    analytics.stream_to(path)
+                        .map_err(|err| anyhow::anyhow!("failed to create {path:?}: {err}"))?;
+                }
+                Ok(analytics)
+            }).transpose()?,
+        };
+        let recorder = match &options.trajectory {
+            Some(_) if options.three_d || replay.is_some() => {
+                log::warn!("trajectories are only recorded from a 2D simulation");
+                None
+            }
+            Some(path) => {
+                // emitters and sinks change the boids' traits as they go
+                let traits_per_frame = !scenario.emitters.is_empty() || !scenario.sinks.is_empty();
+                let recorder = Recorder::create(
+                    path,
+                    &simulation,
+                    &traits,
+                    options.trajectory_every.unwrap_or(1),
+                    options.trajectory_encoding.unwrap_or(Encoding::Delta),
+                    traits_per_frame,
+                ).map_err(|err| anyhow::anyhow!("failed to create {path:?}: {err}"))?;
+                Some(recorder)
+            }
+            None => None,
+        };
+
+        if let Some(err) = device.pop_error_scope().await {
+            return Err(Error::ShaderValidation(err.to_string()).into());
+        }
 
-        Self {
+        Ok(Self {
             surface,
             size,
             device,
             queue,
             config,
+            device_lost,
 
             frame_count,
+            stepped: false,
+            paused: false,
+            step_requested: false,
 
-            boids_buffers,
-            boids_bind_groups,
+            seed,
+            spawner,
+            imported,
+
+            simulation,
+            params,
+
+            scenario,
+            cursor: [0.0, 0.0],
+            overlay,
+            show_flow: false,
 
             camera,
             camera_buffer,
             camera_bind_group,
 
+            coloring,
+            coloring_buffer,
+
+            trails,
+            heatmap,
+
             staging_buffer,
 
-            vertex_buffer,
+            shape,
+            mesh_buffer,
+            mesh_vertex_count: mesh.len() as u32,
+            sprite_vertex_buffer,
 
+            msaa,
+            style,
             render_pipeline,
-            compute_pipeline, 
+            sprite_pipeline,
+            textured_sprite,
+            point_pipeline,
+            culling,
+            lod_enabled: !options.no_lod,
+
+            sim3d,
+
+            gui,
+            show_gui: options.gui,
+            hud,
+            show_hud: options.hud,
+            analytics,
+            profiler,
+            recorder,
+            replay,
+            video,
 
             window,
-        }
+        })
     }
 
     pub fn window(&self) -> &Window {
-        &self.window
+        self.window
+    }
+
+    fn device_lost(&self) -> Option<Error> {
+        self.device_lost.lock().unwrap().take().map(Error::DeviceLost)
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -356,11 +685,41 @@ impl<'a> Renderer<'a> {
             self.config.height = new_size.height;
 
             self.surface.configure(&self.device, &self.config);
-            self.camera.update_scale(new_size);
+            let scene_config = scene_config(&self.config, self.video.as_ref());
+            self.camera.update_scale(winit::dpi::PhysicalSize::new(scene_config.width, scene_config.height));
+            self.trails.resize(&self.device, &scene_config);
+            self.heatmap.resize(&self.device, &scene_config);
+            self.msaa.resize(&self.device, &scene_config);
+            if let Some(sim3d) = &mut self.sim3d {
+                sim3d.resize(&self.device, &scene_config);
+            }
         }
     }
 
     fn update(&mut self) {
+        // this runs for every event, but the boids only take one step per frame drawn,
+        // and none while paused unless one was asked for
+        let step = !self.stepped && (!self.paused || std::mem::take(&mut self.step_requested));
+
+        self.params.frame = self.frame_count as u32;
+        self.write_params();
+
+        if let Some(sim3d) = &self.sim3d {
+            if step {
+                sim3d.update(&self.device, &self.queue, &self.simulation.goals_bind_group, self.frame_count);
+                self.stepped = true;
+            }
+            return;
+        }
+
+        self.simulation.set_cursor(&self.queue, self.cursor);
+
+        let mut lines = self.scenario.overlay_lines(self.cursor);
+        if self.show_flow {
+            lines.extend(self.simulation.flow_field.arrow_lines(self.params.wall_radius, FLOW_ARROWS));
+        }
+        self.overlay.set_lines(&self.queue, &lines);
+
         let mut update_encoder = self.device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor {
                 label: Some("Staging Buffer Encoder"),
@@ -376,35 +735,354 @@ impl<'a> Renderer<'a> {
         self.queue.submit(std::iter::once(update_encoder.finish()));
         self.staging_buffer.recall();
 
+        if !step { return; }
+        self.stepped = true;
+
         let mut compute_encoder = self.device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor {
                 label: Some("Compute Pass Encoder")
             }
         );
 
-        let mut compute_pass = compute_encoder.begin_compute_pass(
-            &wgpu::ComputePassDescriptor {
-                label: Some("Compute Pass"),
-                timestamp_writes: None,
-            }
-        );
+        match &mut self.replay {
+            Some(replay) => replay.update(&self.queue, &self.simulation),
+            None => self.simulation.step(
+                &self.queue,
+                &mut compute_encoder,
+                self.frame_count,
+                self.params.dt,
+                self.profiler.as_ref().and_then(|p| p.compute_writes(Pass::Simulate)),
+            ),
+        }
+        if let Some(analytics) = &mut self.analytics {
+            analytics.record(
+                &self.device,
+                &mut compute_encoder,
+                self.frame_count,
+                self.profiler.as_ref().and_then(|p| p.compute_writes(Pass::Analyze)),
+            );
+        }
 
-        compute_pass.set_pipeline(&self.compute_pipeline);
-        compute_pass.set_bind_group(0, &self.boids_bind_groups[self.frame_count % 2], &[]);
-        compute_pass.dispatch_workgroups(1024, 1, 1);
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.record(&self.device, &mut compute_encoder, &self.simulation, self.frame_count) {
+                log::error!("stopped recording the trajectory: {err}");
+                self.recorder = None;
+            }
+        }
 
-        drop(compute_pass);
+        self.trails.record(&self.queue, &mut compute_encoder, (self.frame_count + 1) % 2);
 
         self.queue.submit(std::iter::once(compute_encoder.finish()));
+        if let Some(analytics) = &mut self.analytics {
+            analytics.map();
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.map();
+        }
+    }
+
+    // Writes out whatever's left of the trajectory or video being recorded, before exiting.
+    fn finish(&mut self) {
+        if let Some(analytics) = &mut self.analytics {
+            analytics.finish(&self.device, &self.queue);
+        }
+        if let Some(recorder) = self.recorder.take() {
+            if let Err(err) = recorder.finish(&self.device) {
+                log::error!("failed to finish the trajectory: {err}");
+            }
+        }
+        self.finish_video();
+    }
+
+    // Stops recording the video and goes back to drawing the scene at the window's size.
+    fn finish_video(&mut self) {
+        let Some(video) = self.video.take() else { return };
+        if let Err(err) = video.finish() {
+            log::error!("failed to finish the video: {err}");
+        }
+        self.resize(self.size);
+    }
+
+    fn write_params(&self) {
+        self.simulation.set_params(&self.queue, self.params);
+        if let Some(sim3d) = &self.sim3d {
+            sim3d.set_params(&self.queue, self.params);
+        }
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.gui.input(event) {
+            return true;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            event: KeyEvent {
+                state: ElementState::Pressed,
+                physical_key: PhysicalKey::Code(keycode),
+                ..
+            },
+            ..
+        } = event {
+            match keycode {
+                KeyCode::KeyI => { self.next_integrator(); return true; }
+                KeyCode::KeyF => { self.show_flow = !self.show_flow; return true; }
+                KeyCode::KeyC => { self.next_color_mode(); return true; }
+                KeyCode::KeyT => {
+                    let mode = self.trails.mode().next();
+                    log::info!("trails: {}", mode.name());
+                    self.trails.set_mode(mode);
+                    return true;
+                }
+                KeyCode::KeyV => { self.next_color_ramp(); return true; }
+                KeyCode::KeyB => {
+                    self.style = self.style.next();
+                    if self.style == BoidStyle::Texture && self.textured_sprite.is_none() {
+                        self.style = self.style.next();
+                    }
+                    log::info!("boid style: {}", self.style.name());
+                    return true;
+                }
+                KeyCode::KeyM => {
+                    // replaces a `--mesh`, which can't be cycled back to
+                    self.shape = self.shape.next();
+                    log::info!("boid shape: {}", self.shape.name());
+                    let mesh = self.shape.vertices();
+                    self.mesh_buffer = create_mesh_buffer(&self.device, &mesh);
+                    self.mesh_vertex_count = mesh.len() as u32;
+                    return true;
+                }
+                KeyCode::KeyL => {
+                    self.lod_enabled = !self.lod_enabled;
+                    log::info!("level of detail: {}", if self.lod_enabled { "on" } else { "off" });
+                    return true;
+                }
+                KeyCode::KeyH => {
+                    let mode = self.heatmap.mode().next();
+                    log::info!("heatmap: {}", mode.name());
+                    self.heatmap.set_mode(mode);
+                    return true;
+                }
+                KeyCode::KeyG => {
+                    self.show_gui = !self.show_gui;
+                    self.window.set_cursor_visible(self.show_gui);
+                    return true;
+                }
+                KeyCode::KeyP => { self.show_hud = !self.show_hud; return true; }
+                _ => if self.replay_input(*keycode) { return true; }
+            }
+        }
+
+        if let Some(sim3d) = &mut self.sim3d {
+            return sim3d.input(event);
+        }
+        if let WindowEvent::CursorMoved { position, .. } = event {
+            self.cursor = self.camera.screen_to_world(*position, self.size);
+        }
         self.camera.process_events(event)
     }
 
+    fn next_integrator(&mut self) {
+        let integrator = Integrator::from_u32(self.params.integrator).next();
+        log::info!("integrator: {}", integrator.name());
+        self.params.integrator = integrator as u32;
+        self.write_params();
+    }
+
+    fn next_color_mode(&mut self) {
+        let mode = self.coloring.mode().next();
+        log::info!("colour mode: {}", mode.name());
+        self.coloring = ColoringUniform::new(mode, None);
+        self.queue.write_buffer(&self.coloring_buffer, 0, bytemuck::cast_slice(&[self.coloring]));
+        self.heatmap.set_ramp(&self.queue, self.coloring.ramp());
+    }
+
+    fn next_color_ramp(&mut self) {
+        let ramp = self.coloring.ramp().next();
+        log::info!("colour ramp: {}", ramp.name());
+        self.coloring.ramp = ramp as u32;
+        self.queue.write_buffer(&self.coloring_buffer, 0, bytemuck::cast_slice(&[self.coloring]));
+        self.heatmap.set_ramp(&self.queue, ramp);
+    }
+
+    // Puts the boids back how the run started, as imported or laid out by the current
+    // spawner, filling the buffers whatever their size was to start with. The 3D
+    // simulation keeps its own boids, so isn't affected, and a replay just goes back to
+    // its first frame.
+    fn reset(&mut self) {
+        if let Some(replay) = &mut self.replay {
+            replay.seek(0);
+            self.trails.clear();
+            return;
+        }
+        let mut rng = rand::rngs::StdRng::seed_from_u64(self.seed);
+        let (boids, traits) = match &self.imported {
+            Some(imported) => imported.clone(),
+            None => self.spawner.spawn(&mut rng, self.simulation.capacity),
+        };
+        self.simulation.reset(&self.queue, &boids, &traits);
+        self.trails.clear();
+    }
+
+    // Declares this frame's GUI panels.
+    fn build_gui(&mut self) {
+        self.gui.begin(self.size);
+        if self.show_gui {
+            match self.replay {
+                Some(_) => self.replay_panel(),
+                None => self.parameter_panel(),
+            }
+        }
+        if self.show_hud {
+            self.hud.show(&mut self.gui, self.profiler.as_ref(), self.analytics.as_ref());
+        }
+        self.gui.end(&self.queue);
+    }
+
+    // The panel for tweaking the simulation, which applies whatever was changed through it.
+    fn parameter_panel(&mut self) {
+        let gui = &mut self.gui;
+        gui.begin_panel(Corner::TopLeft);
+
+        let frame_time = self.hud.frame_time();
+        gui.label(&format!("{:.0} fps {frame_time:.2} ms", 1000.0 / frame_time.max(0.001)));
+        let run = if self.paused { "run" } else { "pause" };
+        let controls: &[&str] = match self.sim3d {
+            Some(_) => &[run, "step"],
+            None => &[run, "step", "reset"],
+        };
+        let control = gui.buttons(controls);
+
+        gui.heading("simulation");
+        let params = &mut self.params;
+        let mut changed = false;
+        changed |= gui.slider("flock radius", &mut params.flock_radius, 0.0, 20.0);
+        changed |= gui.slider("avoid radius", &mut params.avoid_radius, 0.0, 20.0);
+        changed |= gui.slider("wall radius", &mut params.wall_radius, 64.0, 2048.0);
+        changed |= gui.slider("separation", &mut params.separation_weight, 0.0, 10.0);
+        changed |= gui.slider("alignment", &mut params.alignment_weight, 0.0, 5.0);
+        changed |= gui.slider("cohesion", &mut params.cohesion_weight, 0.0, 5.0);
+        changed |= gui.slider("wall", &mut params.wall_weight, 0.0, 50.0);
+        changed |= gui.slider("wander", &mut params.wander_weight, 0.0, 5.0);
+        changed |= gui.slider("flow", &mut params.flow_weight, 0.0, 5.0);
+        changed |= gui.slider("dt", &mut params.dt, 0.01, 1.0);
+        let integrator = gui.choice("integrator", Integrator::from_u32(params.integrator).name());
+
+        gui.heading("display");
+        let color_mode = gui.choice("colour", self.coloring.mode().name());
+        let color_ramp = gui.choice("ramp", self.coloring.ramp().name());
+        let spawner_name = if self.imported.is_some() { "imported" } else { self.spawner.name() };
+        let spawner = self.sim3d.is_none() && gui.choice("spawner", spawner_name);
+
+        gui.end_panel();
+
+        if changed { self.write_params(); }
+        if integrator { self.next_integrator(); }
+        if color_mode { self.next_color_mode(); }
+        if color_ramp { self.next_color_ramp(); }
+        if spawner {
+            // leaving the imported boids goes back to the spawner they replaced
+            if self.imported.take().is_none() {
+                self.spawner = self.spawner.next();
+            }
+            log::info!("spawner: {}", self.spawner.name());
+            self.reset();
+        }
+        match control {
+            Some(0) => self.paused = !self.paused,
+            Some(1) => { self.paused = true; self.step_requested = true; }
+            Some(2) => self.reset(),
+            _ => {}
+        }
+    }
+
+    // The panel for moving around a replay, in place of the parameters it can't change.
+    fn replay_panel(&mut self) {
+        let Some(replay) = &mut self.replay else { return };
+        let gui = &mut self.gui;
+        gui.begin_panel(Corner::TopLeft);
+
+        let frame_time = self.hud.frame_time();
+        gui.label(&format!("{:.0} fps {frame_time:.2} ms", 1000.0 / frame_time.max(0.001)));
+        gui.label(&format!("frame {}/{} step {}", replay.position() + 1, replay.frame_count(), replay.step()));
+        let play = if replay.playing { "pause" } else { "play" };
+        let direction = if replay.reverse { "forward" } else { "reverse" };
+        let control = gui.buttons(&[play, direction, "<", ">"]);
+
+        gui.heading("replay");
+        let mut position = replay.position() as f32;
+        if gui.slider("frame", &mut position, 0.0, (replay.frame_count() - 1) as f32) {
+            replay.seek(position.round() as usize);
+        }
+        gui.slider("speed", &mut replay.speed, 0.1, 10.0);
+
+        gui.heading("display");
+        let color_mode = gui.choice("colour", self.coloring.mode().name());
+        let color_ramp = gui.choice("ramp", self.coloring.ramp().name());
+
+        gui.end_panel();
+
+        match control {
+            Some(0) => replay.playing = !replay.playing,
+            Some(1) => { replay.reverse = !replay.reverse; replay.playing = true; }
+            Some(2) => self.step_replay(-1),
+            Some(3) => self.step_replay(1),
+            _ => {}
+        }
+        if color_mode { self.next_color_mode(); }
+        if color_ramp { self.next_color_ramp(); }
+    }
+
+    // Pauses a replay and moves it `frames` frames on, or back if negative.
+    fn step_replay(&mut self, frames: isize) {
+        let Some(replay) = &mut self.replay else { return };
+        replay.playing = false;
+        replay.seek(replay.position().saturating_add_signed(frames));
+    }
+
+    // Keys for moving around a replay, which only mean anything while there's one.
+    fn replay_input(&mut self, keycode: KeyCode) -> bool {
+        let Some(replay) = &mut self.replay else { return false };
+        match keycode {
+            KeyCode::Space => replay.playing = !replay.playing,
+            KeyCode::KeyR => {
+                replay.reverse = !replay.reverse;
+                log::info!("replaying {}", if replay.reverse { "in reverse" } else { "forwards" });
+            }
+            KeyCode::BracketLeft => replay.speed = (replay.speed / 2.0).max(0.1),
+            KeyCode::BracketRight => replay.speed = (replay.speed * 2.0).min(10.0),
+            KeyCode::Home => replay.seek(0),
+            KeyCode::End => replay.seek(replay.frame_count() - 1),
+            KeyCode::Comma => self.step_replay(-1),
+            KeyCode::Period => self.step_replay(1),
+            _ => return false,
+        }
+        true
+    }
+
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // picks up whatever was read back from earlier frames
+        self.device.poll(wgpu::Maintain::Poll);
+        self.hud.read();
+        if let Some(analytics) = &mut self.analytics {
+            analytics.read();
+        }
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.write() {
+                log::error!("stopped recording the trajectory: {err}");
+                self.recorder = None;
+            }
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.read();
+        }
+        self.hud.frame(self.stepped, self.profiler.as_ref());
+        self.build_gui();
+
         let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let surface_view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // the scene goes into the video being recorded, if there is one, and then the
+        // window shows that
+        let view = self.video.as_ref().map_or(&surface_view, Video::view);
 
         let mut encoder = self.device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor {
@@ -412,20 +1090,133 @@ impl<'a> Renderer<'a> {
             }
         );
 
+        match &self.sim3d {
+            Some(sim3d) => sim3d.render(&mut encoder, view, &self.simulation.traits_buffer, self.frame_count),
+            None => match self.trails.accumulation_target() {
+                Some(target) => {
+                    self.trails.fade(&mut encoder, &self.msaa);
+                    self.render_boids(&mut encoder, target, wgpu::LoadOp::Load);
+                    self.trails.blit(&mut encoder, view);
+                    self.render_overlay(&mut encoder, view);
+                }
+                None => {
+                    self.render_boids(&mut encoder, view, wgpu::LoadOp::Clear(CLEAR_COLOR));
+                    self.render_overlay(&mut encoder, view);
+                }
+            },
+        }
+        // only the frames the boids stepped in make it into the video
+        if let Some(video) = &mut self.video {
+            video.blit(&mut encoder, &surface_view);
+            if self.stepped {
+                video.capture(&mut encoder);
+            }
+        }
+        self.gui.render(&mut encoder, &surface_view);
+
+        self.hud.copy_population(&mut encoder, &self.simulation.population_buffer);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.resolve(&mut encoder);
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        self.hud.map();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.map();
+        }
+        if let Some(video) = &mut self.video {
+            if let Err(err) = video.write(&self.device) {
+                // ffmpeg has already been waited on, so there's nothing left to finish
+                log::error!("stopped recording the video: {err}");
+                self.video = None;
+                self.resize(self.size);
+            }
+        }
+
+        output.present();
+
+        if std::mem::take(&mut self.stepped) {
+            self.frame_count = (self.frame_count + 1) % usize::MAX;
+        }
+        Ok(())
+
+    }
+
+    fn render_boids(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, load: wgpu::LoadOp<wgpu::Color>) {
+        let buffer = (self.frame_count + 1) % 2;
+        let instance_buffer = &self.simulation.boids_buffers[buffer];
+
+        let lod = if self.lod_enabled {
+            Lod::for_boid_pixels(self.camera.pixels_per_unit())
+        } else {
+            Lod::Full
+        };
+        let sprite_vertex_count = SPRITE_VERTICES.len() as u32;
+        let (pipeline, vertices, vertex_count, sprite_bind_group) = match (lod, self.style, &self.textured_sprite) {
+            (Lod::Points, _, _) => (&self.point_pipeline, &self.mesh_buffer, 1, None),
+            (_, BoidStyle::Triangles, _) => (&self.render_pipeline, &self.mesh_buffer, self.mesh_vertex_count, None),
+            (_, BoidStyle::Texture, Some((texture_pipeline, sprite_bind_group))) => {
+                (texture_pipeline, &self.sprite_vertex_buffer, sprite_vertex_count, Some(sprite_bind_group))
+            }
+            _ => (&self.sprite_pipeline, &self.sprite_vertex_buffer, sprite_vertex_count, None),
+        };
+        // every draw below has its instance count written here
+        let cull_writes = self.profiler.as_ref().and_then(|p| p.compute_writes(Pass::Cull));
+        self.culling.cull(&self.queue, encoder, buffer, vertex_count, cull_writes);
+
+        let heatmap = match lod {
+            Lod::Density => HeatmapMode::Only,
+            _ => self.heatmap.mode(),
+        };
+        let load = match heatmap {
+            HeatmapMode::Off => load,
+            mode => {
+                self.heatmap.render(encoder, self.msaa.attachment(view, load), instance_buffer, &self.camera_bind_group, &self.culling.draw_args);
+                if mode == HeatmapMode::Only { return; }
+                wgpu::LoadOp::Load
+            }
+        };
+
         let mut render_pass = encoder.begin_render_pass(
             &wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
+                color_attachments: &[
+                    Some(self.msaa.attachment(view, load)),
+                ],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: self.profiler.as_ref().and_then(|p| p.render_writes(Pass::Render)),
+            }
+        );
+
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        self.trails.render_history(&mut render_pass, &self.simulation.traits_buffer, &self.culling.draw_args);
+
+        render_pass.set_pipeline(pipeline);
+        if let Some(sprite_bind_group) = sprite_bind_group {
+            render_pass.set_bind_group(1, sprite_bind_group, &[]);
+        }
+
+        render_pass.set_vertex_buffer(0, vertices.slice(..));
+        render_pass.set_vertex_buffer(1, self.culling.visible_boids.slice(..));
+        render_pass.set_vertex_buffer(2, self.culling.visible_traits.slice(..));
+        render_pass.set_vertex_buffer(3, self.culling.visible_stats.slice(..));
+
+
+        render_pass.draw_indirect(&self.culling.draw_args, culling::VISIBLE_ARGS);
+    }
+
+    fn render_overlay(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("Overlay Pass"),
                 color_attachments: &[
                     Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
+                        view,
                         resolve_target: None,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.009021491898012131,
-                                g: 0.009021491898012131,
-                                b: 0.023103556157921437,
-                                a: 1.0,
-                            }),
+                            load: wgpu::LoadOp::Load,
                             store: wgpu::StoreOp::Store,
                         }
                     })
@@ -436,42 +1227,167 @@ impl<'a> Renderer<'a> {
             }
         );
 
-        let instance_buffer = &self.boids_buffers[(self.frame_count + 1) % 2];
-
-        render_pass.set_pipeline(&self.render_pipeline);
-
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-
-
-        render_pass.draw(0..VERTICES.len() as u32, 0..N_BOIDS as u32);
-
-        drop(render_pass);
+        self.overlay.render(&mut render_pass, &self.camera_bind_group);
+    }
+}
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+fn create_mesh_buffer(device: &wgpu::Device, vertices: &[[f32; 3]]) -> wgpu::Buffer {
+    device.create_buffer_init(
+        &wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }
+    )
+}
 
-        output.present();
+// The instanced boid pipelines, which share their vertex layout and only differ in the
+// entry points, what they draw and how they're blended.
+#[allow(clippy::too_many_arguments)]
+fn create_boid_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    entry_points: (&str, &str),
+    topology: wgpu::PrimitiveTopology,
+    target: wgpu::ColorTargetState,
+    multisample: wgpu::MultisampleState,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(
+        &wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: entry_points.0,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<Boid>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![1 => Float32x2, 2 => Float32x2],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<BoidTraits>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &[
+                            // color
+                            wgpu::VertexAttribute {
+                                offset: 0,
+                                shader_location: 3,
+                                format: wgpu::VertexFormat::Float32x4,
+                            },
+                            // size, skipping over the species id
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                                shader_location: 4,
+                                format: wgpu::VertexFormat::Float32,
+                            },
+                        ],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<BoidStats>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![5 => Float32, 6 => Float32],
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: entry_points.1,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[
+                    Some(target),
+                ],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample,
+            multiview: None,
+            cache: None,
+        }
+    )
+}
 
-        self.frame_count = (self.frame_count + 1) % usize::MAX;
-        Ok(())
+// What the scene is drawn into: the window's surface, or a video of its own size.
+fn scene_config(config: &wgpu::SurfaceConfiguration, video: Option<&Video>) -> wgpu::SurfaceConfiguration {
+    let mut config = config.clone();
+    if let Some(video) = video {
+        let size = video.size();
+        config.width = size.width;
+        config.height = size.height;
+    }
+    config
+}
 
+// The parameters a run starts with, before anything is changed through the GUI.
+fn sim_params(options: &Options, scenario: &Scenario, interactions: &InteractionMatrix, seed: u64) -> SimParams {
+    let mut params = SimParams::default();
+    params.species_count = interactions.species_count() as u32;
+    if let Some(dt) = options.dt {
+        params.dt = dt;
+    }
+    if let Some(integrator) = options.integrator {
+        params.integrator = integrator as u32;
+    }
+    if let Some(wander) = options.wander {
+        params.wander_weight = wander;
     }
+    if let Some(spec) = &scenario.flow_field {
+        params.flow_weight = spec.weight;
+    }
+    params.seed = seed as u32;
+    params
 }
 
-pub async fn run() {
+pub async fn run() -> anyhow::Result<()> {
     env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
+    let options = Options::from_args();
+    let scenario = match &options.scenario {
+        Some(path) => Scenario::load(path)
+            .map_err(|err| anyhow::anyhow!("failed to load scenario {path:?}: {err}"))?,
+        None => Scenario::default(),
+    };
+    if let Some(path) = &options.bench {
+        return bench::run(&options, &scenario, path).await
+            .map_err(|err| anyhow::anyhow!("benchmark failed: {err}"));
+    }
+    if let Some(path) = &options.sweep {
+        return sweep::run(&options, path).await
+            .map_err(|err| anyhow::anyhow!("sweep {path:?} failed: {err}"));
+    }
+    if let Some(path) = &options.export {
+        return export::run(&options, path)
+            .map_err(|err| anyhow::anyhow!("failed to export {path:?}: {err}"));
+    }
+    let event_loop = EventLoop::new()
+        .map_err(|err| anyhow::anyhow!("failed to start the event loop: {err}"))?;
     let window = WindowBuilder::new()
         .with_decorations(false)
         //.with_inner_size(winit::dpi::PhysicalSize{width: 1280*2, height: 720*2})
-        .build(&event_loop).unwrap();
-    window.set_cursor_visible(false);
+        .build(&event_loop)
+        .map_err(|err| anyhow::anyhow!("failed to open a window: {err}"))?;
 
-    let mut renderer = Renderer::new(&window).await;
+    let mut renderer = Renderer::new(&window, &options, scenario).await?;
     let mut surface_configured = false;
+    // what stopped the event loop, if it wasn't closing the window
+    let mut failure = None;
 
-    let _ = event_loop.run(move |event, control_flow| {
+    event_loop.run(|event, control_flow| {
         if let Event::WindowEvent { window_id, ref event } = event {
             if window_id != renderer.window().id() { return }
             if renderer.input(event) { return }
@@ -488,7 +1404,10 @@ pub async fn run() {
                         ..
                     },
                     ..
-                } => control_flow.exit(),
+                } => {
+                    renderer.finish();
+                    control_flow.exit();
+                }
 
                 WindowEvent::Resized(physical_size) => {
                     log::info!("physical_size: {physical_size:?}");
@@ -519,7 +1438,7 @@ pub async fn run() {
 
                         // system is out of memory so quit
                         Err(wgpu::SurfaceError::OutOfMemory) => {
-                            log::error!("OutOfMemory");
+                            failure = Some(anyhow::anyhow!("the GPU ran out of memory"));
                             control_flow.exit();
                         }
 
@@ -528,10 +1447,16 @@ pub async fn run() {
                             log::warn!("Surface Timeout");
                         }
                     }
+                    if let Some(err) = renderer.device_lost() {
+                        renderer.finish();
+                        failure = Some(err.into());
+                        control_flow.exit();
+                    }
                 }
                 _ => {}
 
             }
         }
-    });
+    }).map_err(|err| anyhow::anyhow!("the event loop failed: {err}"))?;
+    failure.map_or(Ok(()), Err)
 }
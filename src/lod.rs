@@ -0,0 +1,25 @@
+// Level of detail for the 2D boids, picked from how big a boid is on screen. Far enough
+// out that the meshes are a few pixels across, they only add noise, so the boids turn
+// into single pixel points and then into the density heatmap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lod {
+    Full,
+    Points,
+    Density,
+}
+
+// On screen sizes, in pixels, of a size 1 boid below which each level kicks in.
+const POINTS_BELOW: f32 = 6.0;
+const DENSITY_BELOW: f32 = 2.0;
+
+impl Lod {
+    pub fn for_boid_pixels(pixels: f32) -> Self {
+        if pixels < DENSITY_BELOW {
+            Lod::Density
+        } else if pixels < POINTS_BELOW {
+            Lod::Points
+        } else {
+            Lod::Full
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use wgpu_boids::run;
 
 fn main() {
-    pollster::block_on(run());
+    if let Err(err) = pollster::block_on(run()) {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    }
 }
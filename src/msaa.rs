@@ -0,0 +1,91 @@
+// The multisampled colour target the 2D scene is drawn into before being resolved onto
+// the surface, or nothing when multisampling is off.
+pub struct Multisample {
+    sample_count: u32,
+    view: Option<wgpu::TextureView>,
+}
+
+impl Multisample {
+    // Falls back to the largest supported count below `sample_count` if the adapter
+    // can't multisample the surface format that many times.
+    pub fn new(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Self {
+        let flags = adapter.get_texture_format_features(config.format).flags;
+        let supported = [16, 8, 4, 2, 1].into_iter()
+            .filter(|&count| count <= sample_count)
+            .find(|&count| flags.sample_count_supported(count) && device.features().contains(count_feature(count)))
+            .unwrap_or(1);
+        if supported != sample_count {
+            log::warn!("{sample_count}x MSAA isn't supported, using {supported}x");
+        }
+
+        let mut msaa = Self { sample_count: supported, view: None };
+        msaa.resize(device, config);
+        msaa
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.view = (self.sample_count > 1).then(|| create_multisampled_view(device, config, self.sample_count));
+    }
+
+    pub fn state(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        }
+    }
+
+    // An attachment that ends up in `view`, through the multisampled target if there
+    // is one. Loading reads back the multisampled target, not `view`.
+    pub fn attachment<'a>(
+        &'a self,
+        view: &'a wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        let (view, resolve_target) = match &self.view {
+            Some(multisampled) => (multisampled, Some(view)),
+            None => (view, None),
+        };
+        wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            }
+        }
+    }
+}
+
+// Counts other than 1 and 4 depend on the adapter.
+fn count_feature(count: u32) -> wgpu::Features {
+    match count {
+        1 | 4 => wgpu::Features::empty(),
+        _ => wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+    }
+}
+
+fn create_multisampled_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(
+        &wgpu::TextureDescriptor {
+            label: Some("Multisampled Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
@@ -0,0 +1,175 @@
+use crate::params::Integrator;
+use crate::coloring::{ColorMode, ColorRamp};
+use crate::trails::TrailMode;
+use crate::heatmap::HeatmapMode;
+use crate::style::BoidStyle;
+use crate::shape::BoidShape;
+use crate::spawner::Spawner;
+use crate::simulation::NeighbourSearch;
+use crate::trajectory::Encoding;
+
+// Command line flags. Anything we don't recognise is logged and ignored so a typo
+// doesn't stop the window from opening. Flags that take a value use `--flag=value`.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub three_d: bool,
+    pub integrator: Option<Integrator>,
+    pub dt: Option<f32>,
+    pub seed: Option<u64>,
+    pub wander: Option<f32>,
+    pub scenario: Option<String>,
+    pub color_mode: Option<ColorMode>,
+    pub color_ramp: Option<ColorRamp>,
+    pub trails: Option<TrailMode>,
+    pub heatmap: Option<HeatmapMode>,
+    pub msaa: Option<u32>,
+    pub style: Option<BoidStyle>,
+    pub shape: Option<BoidShape>,
+    pub mesh: Option<String>,
+    pub sprite: Option<String>,
+    pub no_lod: bool,
+    pub spawner: Option<Spawner>,
+    pub gui: bool,
+    pub hud: bool,
+    pub analytics: bool,
+    // where to stream the flock metrics, if anywhere
+    pub analytics_csv: Option<String>,
+    pub search: Option<NeighbourSearch>,
+    // where to write the benchmark report, when benchmarking instead of opening a window
+    pub bench: Option<String>,
+    pub bench_counts: Option<Vec<usize>>,
+    pub bench_steps: Option<u32>,
+    // a sweep spec to run instead of opening a window
+    pub sweep: Option<String>,
+    // a file of boids to start with instead of spawning them
+    pub import: Option<String>,
+    // where to record the boids' trajectory, if anywhere
+    pub trajectory: Option<String>,
+    pub trajectory_every: Option<usize>,
+    pub trajectory_encoding: Option<Encoding>,
+    // a trajectory to play back instead of simulating
+    pub replay: Option<String>,
+    // where to record a video, if anywhere, and its size in pixels and frame rate
+    pub record: Option<String>,
+    pub record_size: Option<[u32; 2]>,
+    pub record_fps: Option<u32>,
+    // a trajectory to export instead of opening a window, and where to
+    pub export: Option<String>,
+    pub export_to: Option<String>,
+}
+
+impl Options {
+    pub fn from_args() -> Self {
+        let mut options = Self::default();
+        for arg in std::env::args().skip(1) {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match (flag, value) {
+                ("--3d", None) => options.three_d = true,
+                ("--no-lod", None) => options.no_lod = true,
+                ("--gui", None) => options.gui = true,
+                ("--hud", None) => options.hud = true,
+                ("--analytics", None) => options.analytics = true,
+                ("--analytics", Some(path)) => {
+                    options.analytics = true;
+                    options.analytics_csv = Some(path.to_string());
+                }
+                ("--bench", None) => options.bench = Some("bench.csv".to_string()),
+                ("--bench", Some(path)) => options.bench = Some(path.to_string()),
+                ("--bench-counts", Some(counts)) => match counts.split(',').map(str::parse).collect::<Result<Vec<usize>, _>>() {
+                    Ok(counts) if counts.iter().all(|&count| count > 0) => options.bench_counts = Some(counts),
+                    _ => log::warn!("invalid boid counts {counts:?}"),
+                },
+                ("--bench-steps", Some(steps)) => match steps.parse() {
+                    Ok(steps) if steps > 0 => options.bench_steps = Some(steps),
+                    _ => log::warn!("invalid step count {steps:?}"),
+                },
+                ("--sweep", Some(path)) => options.sweep = Some(path.to_string()),
+                ("--import", Some(path)) => options.import = Some(path.to_string()),
+                ("--trajectory", Some(path)) => options.trajectory = Some(path.to_string()),
+                ("--trajectory-every", Some(every)) => match every.parse() {
+                    Ok(every) if every > 0 => options.trajectory_every = Some(every),
+                    _ => log::warn!("invalid trajectory interval {every:?}"),
+                },
+                ("--trajectory-encoding", Some(name)) => match Encoding::from_name(name) {
+                    Some(encoding) => options.trajectory_encoding = Some(encoding),
+                    None => log::warn!("unknown trajectory encoding {name:?}"),
+                },
+                ("--replay", Some(path)) => options.replay = Some(path.to_string()),
+                ("--record", Some(path)) => options.record = Some(path.to_string()),
+                ("--record-size", Some(size)) => match size.split_once('x').map(|(w, h)| (w.parse(), h.parse())) {
+                    // ffmpeg's yuv420p halves both, so they have to be even
+                    Some((Ok(width), Ok(height))) if width > 0 && height > 0 && width % 2 == 0 && height % 2 == 0 => {
+                        options.record_size = Some([width, height]);
+                    }
+                    _ => log::warn!("invalid video size {size:?}, expected an even width and height e.g. 1920x1080"),
+                },
+                ("--record-fps", Some(fps)) => match fps.parse() {
+                    Ok(fps) if fps > 0 => options.record_fps = Some(fps),
+                    _ => log::warn!("invalid frame rate {fps:?}"),
+                },
+                ("--export", Some(path)) => options.export = Some(path.to_string()),
+                ("--export-to", Some(path)) => options.export_to = Some(path.to_string()),
+                ("--search", Some(name)) => match NeighbourSearch::from_name(name) {
+                    Some(search) => options.search = Some(search),
+                    None => log::warn!("unknown neighbour search {name:?}"),
+                },
+                ("--integrator", Some(name)) => match Integrator::from_name(name) {
+                    Some(integrator) => options.integrator = Some(integrator),
+                    None => log::warn!("unknown integrator {name:?}"),
+                },
+                ("--dt", Some(dt)) => match dt.parse() {
+                    Ok(dt) => options.dt = Some(dt),
+                    Err(_) => log::warn!("invalid time step {dt:?}"),
+                },
+                ("--seed", Some(seed)) => match seed.parse() {
+                    Ok(seed) => options.seed = Some(seed),
+                    Err(_) => log::warn!("invalid seed {seed:?}"),
+                },
+                ("--wander", Some(weight)) => match weight.parse() {
+                    Ok(weight) => options.wander = Some(weight),
+                    Err(_) => log::warn!("invalid wander weight {weight:?}"),
+                },
+                ("--scenario", Some(path)) => options.scenario = Some(path.to_string()),
+                ("--color", Some(name)) => match ColorMode::from_name(name) {
+                    Some(mode) => options.color_mode = Some(mode),
+                    None => log::warn!("unknown colour mode {name:?}"),
+                },
+                ("--ramp", Some(name)) => match ColorRamp::from_name(name) {
+                    Some(ramp) => options.color_ramp = Some(ramp),
+                    None => log::warn!("unknown colour ramp {name:?}"),
+                },
+                ("--trails", Some(name)) => match TrailMode::from_name(name) {
+                    Some(mode) => options.trails = Some(mode),
+                    None => log::warn!("unknown trail mode {name:?}"),
+                },
+                ("--heatmap", Some(name)) => match HeatmapMode::from_name(name) {
+                    Some(mode) => options.heatmap = Some(mode),
+                    None => log::warn!("unknown heatmap mode {name:?}"),
+                },
+                ("--msaa", Some(count)) => match count.parse() {
+                    Ok(count) if u32::is_power_of_two(count) => options.msaa = Some(count),
+                    _ => log::warn!("invalid MSAA sample count {count:?}"),
+                },
+                ("--style", Some(name)) => match BoidStyle::from_name(name) {
+                    Some(style) => options.style = Some(style),
+                    None => log::warn!("unknown boid style {name:?}"),
+                },
+                ("--shape", Some(name)) => match BoidShape::from_name(name) {
+                    Some(shape) => options.shape = Some(shape),
+                    None => log::warn!("unknown boid shape {name:?}"),
+                },
+                ("--spawner", Some(name)) => match Spawner::from_name(name) {
+                    Some(spawner) => options.spawner = Some(spawner),
+                    None => log::warn!("unknown spawner {name:?}"),
+                },
+                ("--mesh", Some(path)) => options.mesh = Some(path.to_string()),
+                ("--sprite", Some(path)) => options.sprite = Some(path.to_string()),
+                _ => log::warn!("ignoring unknown argument {arg:?}"),
+            }
+        }
+        options
+    }
+}
@@ -0,0 +1,120 @@
+use winit::{
+    event::*,
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+// Perspective camera for the 3D mode. It orbits `target` at `distance`, with yaw and
+// pitch driven by the same keys the 2D camera uses for panning.
+pub struct OrbitCamera {
+    target: [f32; 3],
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+    aspect: f32,
+    fovy: f32,
+    znear: f32,
+    zfar: f32,
+}
+
+pub type OrbitCameraUniform = [[f32; 4]; 4];
+
+impl OrbitCamera {
+    pub fn new(viewport_size: winit::dpi::PhysicalSize<u32>) -> Self {
+        Self {
+            target: [0.0, 0.0, 0.0],
+            distance: 320.0,
+            yaw: 0.6,
+            pitch: 0.4,
+            aspect: viewport_size.width as f32 / viewport_size.height as f32,
+            fovy: 45f32.to_radians(),
+            znear: 0.1,
+            zfar: 4096.0,
+        }
+    }
+
+    pub fn update_aspect(&mut self, new_viewport_size: winit::dpi::PhysicalSize<u32>) {
+        self.aspect = new_viewport_size.width as f32 / new_viewport_size.height as f32;
+    }
+
+    pub fn process_events(&mut self, event: &WindowEvent) -> bool {
+        use std::f32::consts::FRAC_PI_2;
+        match event {
+            WindowEvent::KeyboardInput {
+                event: KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(keycode),
+                    ..
+                },
+                ..
+            } => match keycode {
+                KeyCode::KeyW | KeyCode::ArrowUp    => { self.pitch = f32::min( FRAC_PI_2 - 0.01, self.pitch + 0.05); true }
+                KeyCode::KeyS | KeyCode::ArrowDown  => { self.pitch = f32::max(-FRAC_PI_2 + 0.01, self.pitch - 0.05); true }
+                KeyCode::KeyD | KeyCode::ArrowRight => { self.yaw += 0.05; true }
+                KeyCode::KeyA | KeyCode::ArrowLeft  => { self.yaw -= 0.05; true }
+                KeyCode::KeyE  => { self.distance = f32::max(8.0, self.distance * 0.9); true }
+                KeyCode::KeyQ  => { self.distance = f32::min(self.zfar * 0.5, self.distance / 0.9); true }
+                _ => false
+            }
+            _ => false
+        }
+    }
+
+    pub fn eye(&self) -> [f32; 3] {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        let [tx, ty, tz] = self.target;
+        [tx + self.distance * cp * cy, ty + self.distance * sp, tz + self.distance * cp * sy]
+    }
+
+    // Column-major view-projection matrix, with wgpu's 0..1 clip space depth.
+    pub fn to_matrix(&self) -> OrbitCameraUniform {
+        let eye = self.eye();
+        let f = normalize(sub(self.target, eye));
+        let s = normalize(cross(f, [0.0, 1.0, 0.0]));
+        let u = cross(s, f);
+        let view = [
+            [s[0], u[0], -f[0], 0.0],
+            [s[1], u[1], -f[1], 0.0],
+            [s[2], u[2], -f[2], 0.0],
+            [-dot(s, eye), -dot(u, eye), dot(f, eye), 1.0],
+        ];
+
+        let t = 1.0 / (self.fovy / 2.0).tan();
+        let (n, fr) = (self.znear, self.zfar);
+        let proj = [
+            [t / self.aspect, 0.0, 0.0, 0.0],
+            [0.0, t, 0.0, 0.0],
+            [0.0, 0.0, fr / (n - fr), -1.0],
+            [0.0, 0.0, n * fr / (n - fr), 0.0],
+        ];
+
+        mul(proj, view)
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let l = dot(a, a).sqrt();
+    [a[0] / l, a[1] / l, a[2] / l]
+}
+
+fn mul(a: [[f32; 4]; 4], b: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut out = [[0.0; 4]; 4];
+    for (c, col) in out.iter_mut().enumerate() {
+        for (r, v) in col.iter_mut().enumerate() {
+            *v = (0..4).map(|k| a[k][r] * b[c][k]).sum();
+        }
+    }
+    out
+}
@@ -0,0 +1,137 @@
+// World-space line drawing on top of the boids, for things like goal paths. The lines
+// are rebuilt on the CPU whenever they change and drawn with the 2D camera.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OverlayVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl OverlayVertex {
+    pub fn new(position: [f32; 2], color: [f32; 4]) -> Self {
+        Self { position, color }
+    }
+}
+
+pub struct Overlay {
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    pipeline: wgpu::RenderPipeline,
+}
+
+const MAX_OVERLAY_VERTICES: usize = 1 << 16;
+
+impl Overlay {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Overlay Vertex Buffer"),
+                size: (MAX_OVERLAY_VERTICES * std::mem::size_of::<OverlayVertex>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Overlay Pipeline Layout"),
+                bind_group_layouts: &[
+                    camera_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("overlay.wgsl"));
+
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Overlay Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<OverlayVertex>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+                        },
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[
+                        Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }),
+                    ],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            }
+        );
+
+        Self { vertex_buffer, vertex_count: 0, pipeline }
+    }
+
+    // Replaces the lines drawn, as pairs of vertices. Anything past the buffer's
+    // capacity is dropped.
+    pub fn set_lines(&mut self, queue: &wgpu::Queue, vertices: &[OverlayVertex]) {
+        let vertices = &vertices[..vertices.len().min(MAX_OVERLAY_VERTICES) & !1];
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn render<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>, camera_bind_group: &'p wgpu::BindGroup) {
+        if self.vertex_count == 0 { return; }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+// Appends a closed polyline through `points`.
+pub fn push_loop(lines: &mut Vec<OverlayVertex>, points: &[[f32; 2]], color: [f32; 4]) {
+    for (i, &a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        lines.push(OverlayVertex::new(a, color));
+        lines.push(OverlayVertex::new(b, color));
+    }
+}
+
+// Appends an axis aligned cross centred on `center`.
+pub fn push_cross(lines: &mut Vec<OverlayVertex>, center: [f32; 2], size: f32, color: [f32; 4]) {
+    let [x, y] = center;
+    lines.extend_from_slice(&[
+        OverlayVertex::new([x - size, y], color),
+        OverlayVertex::new([x + size, y], color),
+        OverlayVertex::new([x, y - size], color),
+        OverlayVertex::new([x, y + size], color),
+    ]);
+}
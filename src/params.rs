@@ -0,0 +1,119 @@
+// Simulation constants that used to be hardcoded in `compute.wgsl`. These are uploaded
+// as a uniform so they can be tweaked without recompiling the shader.
+//
+// The steering weights are accelerations per unit of simulated time, so changing `dt`
+// changes the step size without changing how strongly boids steer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SimParams {
+    pub flock_radius: f32,
+    pub avoid_radius: f32,
+    pub wall_radius: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    pub wall_weight: f32,
+    pub species_count: u32,
+    pub dt: f32,
+    pub integrator: u32,
+    pub seed: u32,
+    pub frame: u32,
+    pub wander_weight: f32,
+    pub flow_weight: f32,
+    _padding: [u32; 2],
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        Self {
+            flock_radius: 4.0,
+            avoid_radius: 3.0,
+            wall_radius: 512.0,
+            separation_weight: 2.75,
+            alignment_weight: 0.75,
+            cohesion_weight: 0.25,
+            wall_weight: 15.0,
+            species_count: 1,
+            dt: 0.2,
+            integrator: Integrator::Euler as u32,
+            seed: 0,
+            frame: 0,
+            wander_weight: 0.0,
+            flow_weight: 0.0,
+            _padding: [0; 2],
+        }
+    }
+}
+
+impl SimParams {
+    // The float parameters that can be set by name, such as in a sweep.
+    pub const NAMES: [&'static str; 10] = [
+        "flock_radius",
+        "avoid_radius",
+        "wall_radius",
+        "separation_weight",
+        "alignment_weight",
+        "cohesion_weight",
+        "wall_weight",
+        "dt",
+        "wander_weight",
+        "flow_weight",
+    ];
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut f32> {
+        match name {
+            "flock_radius" => Some(&mut self.flock_radius),
+            "avoid_radius" => Some(&mut self.avoid_radius),
+            "wall_radius" => Some(&mut self.wall_radius),
+            "separation_weight" => Some(&mut self.separation_weight),
+            "alignment_weight" => Some(&mut self.alignment_weight),
+            "cohesion_weight" => Some(&mut self.cohesion_weight),
+            "wall_weight" => Some(&mut self.wall_weight),
+            "dt" => Some(&mut self.dt),
+            "wander_weight" => Some(&mut self.wander_weight),
+            "flow_weight" => Some(&mut self.flow_weight),
+            _ => None,
+        }
+    }
+}
+
+// How `cs_main` advances a boid by one step. See `compute.wgsl` for the exact update
+// each of these performs.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    Euler = 0,
+    SemiImplicitEuler = 1,
+    VelocityVerlet = 2,
+    Rk2 = 3,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 4] = [
+        Integrator::Euler,
+        Integrator::SemiImplicitEuler,
+        Integrator::VelocityVerlet,
+        Integrator::Rk2,
+    ];
+
+    pub fn from_u32(value: u32) -> Self {
+        Self::ALL.get(value as usize).copied().unwrap_or(Integrator::Euler)
+    }
+
+    pub fn next(self) -> Self {
+        Self::from_u32((self as u32 + 1) % Self::ALL.len() as u32)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Euler => "euler",
+            Integrator::SemiImplicitEuler => "semi-implicit-euler",
+            Integrator::VelocityVerlet => "verlet",
+            Integrator::Rk2 => "rk2",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|i| i.name() == name)
+    }
+}
@@ -0,0 +1,179 @@
+struct Boid {
+    pos: vec2<f32>,
+    vel: vec2<f32>,
+}
+
+struct BoidTraits {
+    color: vec4<f32>,
+    species: u32,
+    size: f32,
+    max_speed: f32,
+    separation_mul: f32,
+    alignment_mul: f32,
+    cohesion_mul: f32,
+}
+
+struct Area {
+    a: vec2<f32>,
+    b: vec2<f32>,
+    kind: u32,
+}
+
+struct Emitter {
+    area: Area,
+    heading: f32,
+    spread: f32,
+    first_traits: u32,
+    traits_count: u32,
+    first_spawn: u32,
+    spawn_count: u32,
+}
+
+struct Sink {
+    area: Area,
+    species: u32,
+}
+
+struct SpawnParams {
+    seed: u32,
+    frame: u32,
+    spawn_count: u32,
+    capacity: u32,
+}
+
+const TAU = 6.28318530718;
+
+const AREA_POINT = 0u;
+const AREA_RECT = 1u;
+const AREA_CIRCLE = 2u;
+const ANY_SPECIES = 0xffffffffu;
+
+// Keeps spawn randomness apart from `cs_main`'s wander, which hashes the same inputs.
+const SPAWN_SALT = 0x9e3779b9u;
+
+const WORKGROUP_SIZE = 256u;
+
+@group(0) @binding(0) var<storage, read> boids: array<Boid>;
+@group(0) @binding(1) var<storage, read_write> boids_out: array<Boid>;
+@group(0) @binding(2) var<storage, read> traits: array<BoidTraits>;
+@group(0) @binding(3) var<storage, read_write> traits_out: array<BoidTraits>;
+@group(0) @binding(4) var<storage, read_write> population: u32;
+@group(0) @binding(5) var<storage, read> emitters: array<Emitter>;
+@group(0) @binding(6) var<storage, read> sinks: array<Sink>;
+@group(0) @binding(7) var<storage, read> palette: array<BoidTraits>;
+@group(0) @binding(8) var<uniform> params: SpawnParams;
+
+var<workgroup> offsets: array<u32, WORKGROUP_SIZE>;
+
+// Copies the live boids that aren't in a sink to the front of `boids_out`, keeping their
+// order, then appends this frame's spawns after them. It's a single workgroup, so every
+// thread compacts one contiguous chunk and a prefix sum over the chunks' survivor counts
+// says where each chunk goes.
+@compute
+@workgroup_size(WORKGROUP_SIZE)
+fn cs_population(@builtin(local_invocation_index) thread: u32) {
+    let alive = population;
+    let chunk = (alive + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+    let start = min(thread * chunk, alive);
+    let end = min(start + chunk, alive);
+
+    var kept = 0u;
+    for(var i = start; i < end; i++) {
+        if(!sunk(i)) { kept += 1u; }
+    }
+    offsets[thread] = kept;
+    workgroupBarrier();
+
+    // inclusive Hillis-Steele scan
+    for(var stride = 1u; stride < WORKGROUP_SIZE; stride *= 2u) {
+        var before = 0u;
+        if(thread >= stride) { before = offsets[thread - stride]; }
+        workgroupBarrier();
+        offsets[thread] += before;
+        workgroupBarrier();
+    }
+
+    var slot = offsets[thread] - kept;
+    for(var i = start; i < end; i++) {
+        if(sunk(i)) { continue; }
+        boids_out[slot] = boids[i];
+        traits_out[slot] = traits[i];
+        slot += 1u;
+    }
+
+    let survivors = offsets[WORKGROUP_SIZE - 1u];
+    for(var j = thread; j < params.spawn_count; j += WORKGROUP_SIZE) {
+        let slot = survivors + j;
+        if(slot >= params.capacity) { break; }
+        spawn(j, slot);
+    }
+
+    if(thread == 0u) {
+        population = min(survivors + params.spawn_count, params.capacity);
+    }
+}
+
+fn sunk(idx: u32) -> bool {
+    let pos = boids[idx].pos;
+    let species = traits[idx].species;
+    for(var s = 0u; s < arrayLength(&sinks); s++) {
+        let sink = sinks[s];
+        if(sink.species != ANY_SPECIES && sink.species != species) { continue; }
+        if(inside(sink.area, pos)) { return true; }
+    }
+    return false;
+}
+
+fn inside(area: Area, pos: vec2<f32>) -> bool {
+    switch area.kind {
+        case AREA_RECT: { return all(pos >= area.a) && all(pos <= area.b); }
+        case AREA_CIRCLE: { return distance(pos, area.a) <= area.b.x; }
+        default: { return false; }
+    }
+}
+
+// Writes the `j`th new boid this frame into `slot`.
+fn spawn(j: u32, slot: u32) {
+    var emitter = emitters[0];
+    for(var e = 0u; e < arrayLength(&emitters); e++) {
+        emitter = emitters[e];
+        if(j >= emitter.first_spawn && j < emitter.first_spawn + emitter.spawn_count) { break; }
+    }
+
+    var state = pcg_hash(j ^ pcg_hash(params.frame ^ pcg_hash(params.seed ^ SPAWN_SALT)));
+
+    var pos = emitter.area.a;
+    switch emitter.area.kind {
+        case AREA_RECT: {
+            let t = vec2<f32>(random_f32(&state), random_f32(&state));
+            pos = mix(emitter.area.a, emitter.area.b, t);
+        }
+        case AREA_CIRCLE: {
+            let r = emitter.area.b.x * sqrt(random_f32(&state));
+            let a = random_f32(&state) * TAU;
+            pos = emitter.area.a + r * vec2<f32>(cos(a), sin(a));
+        }
+        default: {}
+    }
+
+    let pick = min(u32(random_f32(&state) * f32(emitter.traits_count)), emitter.traits_count - 1u);
+    let boid_traits = palette[emitter.first_traits + pick];
+
+    let heading = emitter.heading + (random_f32(&state) - 0.5) * emitter.spread;
+    let vel = vec2<f32>(cos(heading), sin(heading)) * boid_traits.max_speed;
+
+    boids_out[slot] = Boid(pos, vel);
+    traits_out[slot] = boid_traits;
+}
+
+// PCG output permutation, used as a stateless hash.
+fn pcg_hash(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random_f32(state: ptr<function, u32>) -> f32 {
+    *state = pcg_hash(*state);
+    return f32(*state >> 8u) / 16777216.0;
+}
@@ -0,0 +1,145 @@
+use std::cell::Cell;
+
+use crate::readback::Readback;
+
+// The passes that are timed on the GPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    Simulate,
+    Analyze,
+    Cull,
+    Render,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 4] = [
+        Pass::Simulate,
+        Pass::Analyze,
+        Pass::Cull,
+        Pass::Render,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Simulate => "simulate",
+            Pass::Analyze => "analyze",
+            Pass::Cull => "cull",
+            Pass::Render => "render",
+        }
+    }
+}
+
+const QUERY_COUNT: u32 = 2 * Pass::ALL.len() as u32;
+
+// How much of each new timing goes into the smoothed one.
+const SMOOTHING: f32 = 0.1;
+
+// Times passes on the GPU with timestamp queries, which not every adapter supports.
+// Each timed pass writes a timestamp at its start and end, and those are read back a
+// frame or two later. Passes are only timed while the last timings are in flight.
+pub struct Profiler {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback: Readback,
+    // nanoseconds per tick
+    period: f32,
+    // a bit for each pass given timestamp writes since the last resolve
+    written: Cell<u32>,
+    // the passes' bits for the timestamps being read back
+    resolved: u32,
+    // smoothed, in milliseconds
+    timings: [Option<f32>; Pass::ALL.len()],
+}
+
+impl Profiler {
+    // Returns `None` if the device wasn't created with `TIMESTAMP_QUERY`.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            log::info!("GPU timestamps aren't supported, so passes won't be timed");
+            return None;
+        }
+
+        let query_set = device.create_query_set(
+            &wgpu::QuerySetDescriptor {
+                label: Some("Profiler Query Set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: QUERY_COUNT,
+            }
+        );
+        let size = QUERY_COUNT as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+        let resolve_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Profiler Resolve Buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }
+        );
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback: Readback::new(device, "Profiler Readback Buffer", size),
+            period: queue.get_timestamp_period(),
+            written: Cell::new(0),
+            resolved: 0,
+            timings: [None; Pass::ALL.len()],
+        })
+    }
+
+    fn writes(&self, pass: Pass) -> Option<(&wgpu::QuerySet, u32)> {
+        if self.readback.busy() { return None; }
+        self.written.set(self.written.get() | 1 << pass as u32);
+        Some((&self.query_set, 2 * pass as u32))
+    }
+
+    pub fn compute_writes(&self, pass: Pass) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        self.writes(pass).map(|(query_set, index)| wgpu::ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    pub fn render_writes(&self, pass: Pass) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.writes(pass).map(|(query_set, index)| wgpu::RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    // Records copying this frame's timestamps back, after the last timed pass.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let written = self.written.take();
+        if written == 0 { return; }
+        encoder.resolve_query_set(&self.query_set, 0..QUERY_COUNT, &self.resolve_buffer, 0);
+        self.readback.copy(encoder, &self.resolve_buffer);
+        self.resolved = written;
+    }
+
+    // Call once the commands from `resolve` have been submitted.
+    pub fn map(&mut self) {
+        self.readback.map();
+    }
+
+    // Takes in the last timestamps, if they've arrived.
+    pub fn read(&mut self) {
+        let Some(timestamps) = self.readback.read::<u64>() else { return };
+        for pass in Pass::ALL {
+            if self.resolved & 1 << pass as u32 == 0 { continue; }
+            let [start, end] = [timestamps[2 * pass as usize], timestamps[2 * pass as usize + 1]];
+            let ms = end.saturating_sub(start) as f32 * self.period / 1e6;
+            let timing = &mut self.timings[pass as usize];
+            *timing = Some(match *timing {
+                Some(smoothed) => smoothed + (ms - smoothed) * SMOOTHING,
+                None => ms,
+            });
+        }
+    }
+
+    // The smoothed time each pass takes, in milliseconds, if it's been timed.
+    pub fn timing(&self, pass: Pass) -> Option<f32> {
+        self.timings[pass as usize]
+    }
+}
@@ -0,0 +1,52 @@
+// Colour ramps shared by the shaders that map values to colours. Prepended to them
+// when the shader module is created.
+
+const RAMP_MAGMA = 1u;
+const RAMP_COOLWARM = 2u;
+const RAMP_GRAYSCALE = 3u;
+const RAMP_RAINBOW = 4u;
+
+// sRGB control points, evenly spaced along each ramp
+const VIRIDIS = array<vec3<f32>, 5>(
+    vec3<f32>(0.267, 0.005, 0.329),
+    vec3<f32>(0.231, 0.322, 0.545),
+    vec3<f32>(0.129, 0.569, 0.549),
+    vec3<f32>(0.369, 0.788, 0.384),
+    vec3<f32>(0.992, 0.906, 0.145),
+);
+const MAGMA = array<vec3<f32>, 5>(
+    vec3<f32>(0.000, 0.000, 0.016),
+    vec3<f32>(0.318, 0.071, 0.486),
+    vec3<f32>(0.718, 0.216, 0.475),
+    vec3<f32>(0.988, 0.537, 0.380),
+    vec3<f32>(0.988, 0.992, 0.749),
+);
+const COOLWARM = array<vec3<f32>, 5>(
+    vec3<f32>(0.231, 0.298, 0.753),
+    vec3<f32>(0.553, 0.690, 0.996),
+    vec3<f32>(0.867, 0.867, 0.867),
+    vec3<f32>(0.957, 0.604, 0.482),
+    vec3<f32>(0.706, 0.016, 0.149),
+);
+
+fn ramp(which: u32, t: f32) -> vec3<f32> {
+    let x = clamp(t, 0.0, 1.0);
+    if(which == RAMP_RAINBOW) {
+        let hue = abs(fract(x + vec3<f32>(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0;
+        return srgb_to_linear(clamp(hue, vec3<f32>(0.0), vec3<f32>(1.0)));
+    }
+    if(which == RAMP_GRAYSCALE) { return srgb_to_linear(vec3<f32>(x)); }
+
+    var points = VIRIDIS;
+    if(which == RAMP_MAGMA) { points = MAGMA; }
+    if(which == RAMP_COOLWARM) { points = COOLWARM; }
+
+    let scaled = x * 4.0;
+    let i = min(u32(scaled), 3u);
+    return srgb_to_linear(mix(points[i], points[i + 1], scaled - f32(i)));
+}
+
+// The surface is sRGB, so colours written here are expected to be linear.
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return pow(c, vec3<f32>(2.2));
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Copies results from the GPU back to the CPU without ever waiting on them. A new copy
+// is only recorded once the last one has been read, so frames are skipped while one is
+// in flight, and nothing arrives unless `wgpu::Device::poll` is called now and then.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    // a copy has been recorded but not mapped yet
+    copied: bool,
+    // the buffer has been asked to map and hasn't been read since
+    mapping: bool,
+    mapped: Arc<AtomicBool>,
+}
+
+impl Readback {
+    pub fn new(device: &wgpu::Device, label: &str, size: wgpu::BufferAddress) -> Self {
+        let buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+        Self {
+            buffer,
+            copied: false,
+            mapping: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn busy(&self) -> bool {
+        self.copied || self.mapping
+    }
+
+    // Records a copy of the start of `source`, unless the last one is still in flight.
+    pub fn copy(&mut self, encoder: &mut wgpu::CommandEncoder, source: &wgpu::Buffer) {
+        if self.busy() { return; }
+        encoder.copy_buffer_to_buffer(source, 0, &self.buffer, 0, self.buffer.size());
+        self.copied = true;
+    }
+
+    // Records copies of the whole of each of `sources`, end to end, unless the last copy
+    // is still in flight.
+    pub fn copy_many(&mut self, encoder: &mut wgpu::CommandEncoder, sources: &[&wgpu::Buffer]) {
+        if self.busy() { return; }
+        let mut offset = 0;
+        for source in sources {
+            encoder.copy_buffer_to_buffer(source, 0, &self.buffer, offset, source.size());
+            offset += source.size();
+        }
+        self.copied = true;
+    }
+
+    // Records a copy of the whole of `texture`, with its rows `bytes_per_row` apart,
+    // unless the last copy is still in flight.
+    pub fn copy_texture(&mut self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, bytes_per_row: u32) {
+        if self.busy() { return; }
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        self.copied = true;
+    }
+
+    // Call once the commands with the copy have been submitted.
+    pub fn map(&mut self) {
+        if !self.copied { return; }
+        self.copied = false;
+        self.mapping = true;
+        let mapped = self.mapped.clone();
+        self.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            if result.is_ok() {
+                mapped.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    pub fn read<T: bytemuck::Pod>(&mut self) -> Option<Vec<T>> {
+        if !self.mapping || !self.mapped.swap(false, Ordering::Acquire) {
+            return None;
+        }
+        let data = bytemuck::cast_slice(&self.buffer.slice(..).get_mapped_range()).to_vec();
+        self.buffer.unmap();
+        self.mapping = false;
+        Some(data)
+    }
+}
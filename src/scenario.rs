@@ -0,0 +1,395 @@
+use serde::Deserialize;
+use bytemuck::Zeroable;
+
+use crate::overlay::{self, OverlayVertex};
+use crate::flow_field::FlowField;
+use crate::boid::{self, Interaction, InteractionMatrix};
+
+// Everything about a run that isn't a simulation constant, such as what the boids are
+// steering towards. Loaded from JSON with `--scenario=<path>`, e.g.
+//
+//     { "goals": [{ "species": 1, "weight": 0.5,
+//                   "target": { "path": { "waypoints": [[-200, 0], [0, 200], [200, 0]] } } }] }
+//
+// Species only flock with their own kind unless `interactions` says otherwise, e.g. for
+// species 1 hunting species 0, which flees:
+//
+//     { "interactions": [[{ "separation": 1, "alignment": 1, "cohesion": 1 }, { "separation": 4 }],
+//                        [{ "separation": 1, "cohesion": 2 }, { "separation": 1, "alignment": 1, "cohesion": 1 }]] }
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub goals: Vec<Goal>,
+    pub flow_field: Option<FlowFieldSpec>,
+    pub emitters: Vec<Emitter>,
+    pub sinks: Vec<Sink>,
+    // How many boids are alive at the start, up to the buffers' capacity. All of them
+    // if it's left out.
+    pub population: Option<u32>,
+    // A row per species of how it reacts to each species, see `InteractionMatrix`.
+    pub interactions: Option<Vec<Vec<Interaction>>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Goal {
+    // Only boids of this species seek the goal. All of them do if it's left out.
+    #[serde(default)]
+    pub species: Option<u32>,
+    #[serde(default = "Goal::default_weight")]
+    pub weight: f32,
+    pub target: GoalTarget,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum GoalTarget {
+    Point([f32; 2]),
+    // A closed loop. Boids head for the point `lookahead` units further along the path
+    // from wherever they're closest to it, so they don't need to remember a waypoint.
+    Path {
+        waypoints: Vec<[f32; 2]>,
+        #[serde(default = "GoalTarget::default_lookahead")]
+        lookahead: f32,
+    },
+    Cursor,
+}
+
+// Spawns `rate` boids per unit of simulation time somewhere in `area`, heading within
+// `spread` degrees around `heading`, counterclockwise from +x.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Emitter {
+    // Spawned boids get the traits of a random entry of `boid::SPECIES` of this species,
+    // or of any entry if it's left out.
+    #[serde(default)]
+    pub species: Option<u32>,
+    pub rate: f32,
+    pub area: Area,
+    #[serde(default)]
+    pub heading: f32,
+    #[serde(default = "Emitter::default_spread")]
+    pub spread: f32,
+}
+
+// Removes boids that fly into `area`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sink {
+    // Only boids of this species are removed. All of them are if it's left out.
+    #[serde(default)]
+    pub species: Option<u32>,
+    pub area: Area,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Area {
+    Point([f32; 2]),
+    Rect { min: [f32; 2], max: [f32; 2] },
+    Circle { centre: [f32; 2], radius: f32 },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlowFieldSpec {
+    pub source: FlowFieldSource,
+    #[serde(default = "FlowFieldSpec::default_weight")]
+    pub weight: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum FlowFieldSource {
+    // Path to a PNG, see `FlowField::from_png`.
+    Image(String),
+    CurlNoise {
+        #[serde(default = "FlowFieldSource::default_resolution")]
+        resolution: u32,
+        #[serde(default = "FlowFieldSource::default_scale")]
+        scale: f32,
+    },
+}
+
+impl FlowFieldSpec {
+    fn default_weight() -> f32 {
+        0.5
+    }
+
+    pub fn build(&self, seed: u64) -> anyhow::Result<FlowField> {
+        match &self.source {
+            FlowFieldSource::Image(path) => FlowField::from_png(path)
+                .map_err(|err| anyhow::anyhow!("failed to load {path:?}: {err}")),
+            FlowFieldSource::CurlNoise { resolution, scale } => Ok(FlowField::curl_noise(*resolution, *scale, seed)),
+        }
+    }
+}
+
+impl FlowFieldSource {
+    fn default_resolution() -> u32 {
+        128
+    }
+
+    fn default_scale() -> f32 {
+        4.0
+    }
+}
+
+impl Goal {
+    fn default_weight() -> f32 {
+        0.5
+    }
+}
+
+impl GoalTarget {
+    fn default_lookahead() -> f32 {
+        16.0
+    }
+}
+
+impl Emitter {
+    fn default_spread() -> f32 {
+        360.0
+    }
+}
+
+// Segments in the overlay's circles.
+const CIRCLE_SEGMENTS: usize = 32;
+
+impl Area {
+    // Outline for the overlay, as a closed loop. Empty for points.
+    fn outline(&self) -> Vec<[f32; 2]> {
+        match *self {
+            Area::Point(_) => Vec::new(),
+            Area::Rect { min, max } => vec![min, [max[0], min[1]], max, [min[0], max[1]]],
+            Area::Circle { centre, radius } => (0..CIRCLE_SEGMENTS)
+                .map(|i| {
+                    let a = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                    [centre[0] + a.cos() * radius, centre[1] + a.sin() * radius]
+                })
+                .collect(),
+        }
+    }
+
+    fn push_overlay(&self, lines: &mut Vec<OverlayVertex>, color: [f32; 4]) {
+        match self {
+            Area::Point(point) => overlay::push_cross(lines, *point, 4.0, color),
+            _ => overlay::push_loop(lines, &self.outline(), color),
+        }
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let scenario: Self = serde_json::from_reader(std::io::BufReader::new(file))?;
+        for goal in &scenario.goals {
+            if let GoalTarget::Path { waypoints, .. } = &goal.target {
+                anyhow::ensure!(waypoints.len() >= 2, "a path needs at least two waypoints");
+            }
+        }
+        if let Some(FlowFieldSpec { source: FlowFieldSource::CurlNoise { resolution, .. }, .. }) = &scenario.flow_field {
+            anyhow::ensure!(*resolution > 0, "a curl noise flow field needs a non-zero resolution");
+        }
+        for emitter in &scenario.emitters {
+            anyhow::ensure!(emitter.rate >= 0.0, "an emitter's rate can't be negative");
+            if let Some(species) = emitter.species {
+                anyhow::ensure!(
+                    boid::SPECIES.iter().any(|t| t.species() == species),
+                    "there are no boids of species {species} to emit",
+                );
+            }
+        }
+        for sink in &scenario.sinks {
+            anyhow::ensure!(!matches!(sink.area, Area::Point(_)), "a sink needs an area, not a point");
+        }
+        if let Some(rows) = &scenario.interactions {
+            InteractionMatrix::from_rows(rows)?;
+        }
+        Ok(scenario)
+    }
+
+    // How the species react to each other, which is only to flock with their own kind
+    // unless the scenario has a table.
+    pub fn interactions(&self) -> InteractionMatrix {
+        match &self.interactions {
+            Some(rows) => InteractionMatrix::from_rows(rows).expect("the table was checked when the scenario was loaded"),
+            None => InteractionMatrix::segregated(boid::species_count()),
+        }
+    }
+
+    pub fn overlay_lines(&self, cursor: [f32; 2]) -> Vec<OverlayVertex> {
+        let mut lines = Vec::new();
+        for goal in &self.goals {
+            match &goal.target {
+                GoalTarget::Point(point) => overlay::push_cross(&mut lines, *point, 4.0, GOAL_COLOR),
+                GoalTarget::Path { waypoints, .. } => overlay::push_loop(&mut lines, waypoints, GOAL_COLOR),
+                GoalTarget::Cursor => overlay::push_cross(&mut lines, cursor, 4.0, GOAL_COLOR),
+            }
+        }
+        for emitter in &self.emitters {
+            emitter.area.push_overlay(&mut lines, EMITTER_COLOR);
+        }
+        for sink in &self.sinks {
+            sink.area.push_overlay(&mut lines, SINK_COLOR);
+        }
+        lines
+    }
+}
+
+const GOAL_COLOR: [f32; 4] = [0.2, 0.6, 0.5, 0.6];
+const EMITTER_COLOR: [f32; 4] = [0.3, 0.7, 0.3, 0.6];
+const SINK_COLOR: [f32; 4] = [0.7, 0.3, 0.3, 0.6];
+
+pub const GOAL_POINT: u32 = 0;
+pub const GOAL_PATH: u32 = 1;
+pub const GOAL_CURSOR: u32 = 2;
+
+pub const ANY_SPECIES: u32 = u32::MAX;
+
+// GPU layout of a `Goal`. Path waypoints are stored in a separate buffer, and referenced
+// by range.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GoalUniform {
+    pub point: [f32; 2],
+    pub kind: u32,
+    pub species: u32,
+    pub weight: f32,
+    pub lookahead: f32,
+    pub first_waypoint: u32,
+    pub waypoint_count: u32,
+}
+
+// Flattens the goals into the two buffers `cs_main` reads. Both are padded to at least
+// one element since wgpu doesn't allow empty storage bindings, and the padding goal has
+// zero weight.
+pub fn goal_buffers(goals: &[Goal]) -> (Vec<GoalUniform>, Vec<[f32; 2]>) {
+    let mut uniforms = Vec::new();
+    let mut waypoints = Vec::new();
+    for goal in goals {
+        let mut uniform = GoalUniform {
+            point: [0.0, 0.0],
+            kind: GOAL_POINT,
+            species: goal.species.unwrap_or(ANY_SPECIES),
+            weight: goal.weight,
+            lookahead: 0.0,
+            first_waypoint: 0,
+            waypoint_count: 0,
+        };
+        match &goal.target {
+            GoalTarget::Point(point) => uniform.point = *point,
+            GoalTarget::Path { waypoints: path, lookahead } => {
+                uniform.kind = GOAL_PATH;
+                uniform.lookahead = *lookahead;
+                uniform.first_waypoint = waypoints.len() as u32;
+                uniform.waypoint_count = path.len() as u32;
+                waypoints.extend_from_slice(path);
+            }
+            GoalTarget::Cursor => uniform.kind = GOAL_CURSOR,
+        }
+        uniforms.push(uniform);
+    }
+
+    if uniforms.is_empty() {
+        uniforms.push(GoalUniform::zeroed());
+    }
+    if waypoints.is_empty() {
+        waypoints.push([0.0, 0.0]);
+    }
+    (uniforms, waypoints)
+}
+
+pub const AREA_POINT: u32 = 0;
+pub const AREA_RECT: u32 = 1;
+pub const AREA_CIRCLE: u32 = 2;
+
+// GPU layout of an `Area`. Points and circles keep their centre in `a`, and a circle's
+// radius is `b[0]`. Rects go from `a` to `b`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AreaUniform {
+    pub a: [f32; 2],
+    pub b: [f32; 2],
+    pub kind: u32,
+    pub _padding: u32,
+}
+
+impl From<&Area> for AreaUniform {
+    fn from(area: &Area) -> Self {
+        let (kind, a, b) = match *area {
+            Area::Point(point) => (AREA_POINT, point, [0.0, 0.0]),
+            Area::Rect { min, max } => (AREA_RECT, min, max),
+            Area::Circle { centre, radius } => (AREA_CIRCLE, centre, [radius, 0.0]),
+        };
+        Self { a, b, kind, _padding: 0 }
+    }
+}
+
+// GPU layout of an `Emitter`. The spawn range is which of this frame's new boids come
+// from this emitter, and is filled in every frame.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EmitterUniform {
+    pub area: AreaUniform,
+    pub heading: f32,
+    pub spread: f32,
+    pub first_traits: u32,
+    pub traits_count: u32,
+    pub first_spawn: u32,
+    pub spawn_count: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SinkUniform {
+    pub area: AreaUniform,
+    pub species: u32,
+    pub _padding: u32,
+}
+
+// Flattens the emitters and sinks into the buffers `cs_population` reads, along with the
+// traits emitted boids are picked from, which is `boid::SPECIES` grouped by species.
+// Like `goal_buffers`, each list is padded to at least one element, and the padding sink
+// is a point so removes nothing.
+pub fn population_buffers(emitters: &[Emitter], sinks: &[Sink]) -> (Vec<EmitterUniform>, Vec<SinkUniform>, Vec<boid::BoidTraits>) {
+    let mut palette = boid::SPECIES.to_vec();
+    palette.sort_by_key(|t| t.species());
+
+    let mut emitter_uniforms: Vec<EmitterUniform> = emitters.iter()
+        .map(|emitter| {
+            let (first_traits, traits_count) = match emitter.species {
+                Some(species) => {
+                    let first = palette.iter().position(|t| t.species() == species).unwrap_or(0);
+                    let count = palette.iter().filter(|t| t.species() == species).count();
+                    (first as u32, count as u32)
+                }
+                None => (0, palette.len() as u32),
+            };
+            EmitterUniform {
+                area: (&emitter.area).into(),
+                heading: emitter.heading.to_radians(),
+                spread: emitter.spread.to_radians(),
+                first_traits,
+                traits_count,
+                first_spawn: 0,
+                spawn_count: 0,
+            }
+        })
+        .collect();
+    let mut sink_uniforms: Vec<SinkUniform> = sinks.iter()
+        .map(|sink| SinkUniform {
+            area: (&sink.area).into(),
+            species: sink.species.unwrap_or(ANY_SPECIES),
+            _padding: 0,
+        })
+        .collect();
+
+    if emitter_uniforms.is_empty() {
+        emitter_uniforms.push(EmitterUniform::zeroed());
+    }
+    if sink_uniforms.is_empty() {
+        sink_uniforms.push(SinkUniform::zeroed());
+    }
+    (emitter_uniforms, sink_uniforms, palette)
+}
@@ -9,17 +9,95 @@ struct BoidInstance {
     @location(2) vel: vec2<f32>,
 }
 
+struct BoidTraits {
+    @location(3) color: vec4<f32>,
+    @location(4) size: f32,
+}
+
+struct BoidStats {
+    @location(5) neighbours: f32,
+    @location(6) centre_dst: f32,
+}
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) @interpolate(flat) value: f32,
+    // position in the boid's own frame, before scaling by its size
+    @location(2) local: vec2<f32>,
+}
+
+struct Coloring {
+    mode: u32,
+    ramp: u32,
+    range: vec2<f32>,
 }
 
+const COLOR_SPECIES = 0u;
+const COLOR_HEADING = 1u;
+const COLOR_SPEED = 2u;
+const COLOR_DENSITY = 3u;
+const COLOR_FLOCK_CENTRE = 4u;
+
+// Corners of the boid triangle, matching `BoidShape::Triangle`.
+const TRIANGLE = array<vec2<f32>, 3>(
+    vec2<f32>( 1.00000,  0.00000),
+    vec2<f32>(-0.83147,  0.55557),
+    vec2<f32>(-0.83147, -0.55557),
+);
+
+// Sprites are a little larger than the triangle so its anti-aliased edge isn't cut off.
+const SPRITE_EXTENT = 1.25;
+
 @group(0) @binding(0)
 var<uniform> camera_mat: mat3x3<f32>;
 
+@group(0) @binding(1)
+var<uniform> coloring: Coloring;
+
+// Only bound for the textured sprites.
+@group(1) @binding(0)
+var sprite: texture_2d<f32>;
+
+@group(1) @binding(1)
+var sprite_sampler: sampler;
+
 @vertex
 fn vs_main(
     vertex: VertexInput,
     instance: BoidInstance,
+    traits: BoidTraits,
+    stats: BoidStats,
+) -> VertexOutput {
+    return boid_vertex(vertex.position.xy, instance, traits, stats);
+}
+
+// Sprites are drawn on a quad, `vertex` is one of its corners in -1..1.
+@vertex
+fn vs_sprite(
+    vertex: VertexInput,
+    instance: BoidInstance,
+    traits: BoidTraits,
+    stats: BoidStats,
+) -> VertexOutput {
+    return boid_vertex(vertex.position.xy * SPRITE_EXTENT, instance, traits, stats);
+}
+
+// Far out boids are single points, coloured like the meshes.
+@vertex
+fn vs_point(
+    instance: BoidInstance,
+    traits: BoidTraits,
+    stats: BoidStats,
+) -> VertexOutput {
+    return boid_vertex(vec2<f32>(0.0, 0.0), instance, traits, stats);
+}
+
+fn boid_vertex(
+    local: vec2<f32>,
+    instance: BoidInstance,
+    traits: BoidTraits,
+    stats: BoidStats,
 ) -> VertexOutput {
     var out: VertexOutput;
 
@@ -34,13 +112,70 @@ fn vs_main(
     );
 
 
-    let clip_position = camera_mat * instance_mat * vec3<f32>(vertex.position);
+    let local_position = vec3<f32>(local * traits.size, 1.0);
+    let clip_position = camera_mat * instance_mat * local_position;
     out.clip_position = vec4<f32>(clip_position, 1.0);
+    out.color = traits.color;
+    out.local = local;
+
+    switch coloring.mode {
+        case COLOR_HEADING: { out.value = rot / (2.0 * PI) + 0.5; }
+        case COLOR_SPEED: { out.value = length(instance.vel); }
+        case COLOR_DENSITY: { out.value = stats.neighbours; }
+        case COLOR_FLOCK_CENTRE: { out.value = stats.centre_dst; }
+        default: { out.value = 0.0; }
+    }
 
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(0.11658, 0.05112, 0.38891, 1.0);
+    return boid_color(in);
+}
+
+// The triangle's signed distance field, faded out over about a pixel at its edge.
+@fragment
+fn fs_sprite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let d = triangle_sdf(in.local);
+    let coverage = clamp(0.5 - d / fwidth(d), 0.0, 1.0);
+    if(coverage == 0.0) { discard; }
+
+    let color = boid_color(in);
+    return vec4<f32>(color.rgb, color.a * coverage);
+}
+
+// The image faces +x, like the meshes, and is tinted by the boid's colour.
+@fragment
+fn fs_texture(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = vec2<f32>(in.local.x, -in.local.y) / (2.0 * SPRITE_EXTENT) + 0.5;
+    let texel = textureSample(sprite, sprite_sampler, uv);
+    if(texel.a == 0.0) { discard; }
+
+    return texel * boid_color(in);
+}
+
+fn boid_color(in: VertexOutput) -> vec4<f32> {
+    if(coloring.mode == COLOR_SPECIES) { return in.color; }
+
+    let t = (in.value - coloring.range.x) / (coloring.range.y - coloring.range.x);
+    return vec4<f32>(ramp(coloring.ramp, t), 1.0);
+}
+
+// Signed distance from `p` to the edge of `TRIANGLE`, negative inside.
+fn triangle_sdf(p: vec2<f32>) -> f32 {
+    var corners = TRIANGLE;
+    var d = 3.4e38;
+    var s = 1.0;
+    for(var i = 0; i < 3; i++) {
+        let a = corners[i];
+        let b = corners[(i + 1) % 3];
+        let e = b - a;
+        let w = p - a;
+        let q = w - e * clamp(dot(w, e) / dot(e, e), 0.0, 1.0);
+        d = min(d, dot(q, q));
+        // the triangle winds anticlockwise, so `p` is inside if it's left of every edge
+        if(e.x * w.y - e.y * w.x < 0.0) { s = -1.0; }
+    }
+    return -s * sqrt(d);
 }
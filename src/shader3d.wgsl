@@ -0,0 +1,59 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct BoidInstance {
+    @location(1) pos: vec3<f32>,
+    @location(2) vel: vec3<f32>,
+}
+
+struct BoidTraits {
+    @location(3) color: vec4<f32>,
+    @location(4) size: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera_mat: mat4x4<f32>;
+
+@vertex
+fn vs_main(
+    vertex: VertexInput,
+    instance: BoidInstance,
+    traits: BoidTraits,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    // the mesh points down +x, so build a basis with x along the heading
+    let forward = normalize(instance.vel);
+    var up = vec3<f32>(0, 1, 0);
+    if(abs(forward.y) > 0.99) { up = vec3<f32>(1, 0, 0); }
+    let right = normalize(cross(forward, up));
+    let normal = cross(right, forward);
+
+    let local = vertex.position * traits.size;
+    let world_position = instance.pos
+                       + forward * local.x
+                       + normal  * local.y
+                       + right   * local.z;
+
+    out.clip_position = camera_mat * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+    out.color = traits.color;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // flat shading from screen-space derivatives, so the mesh doesn't need normals
+    let normal = normalize(cross(dpdx(in.world_position), dpdy(in.world_position)));
+    let light = normalize(vec3<f32>(0.4, 1.0, 0.3));
+    let shade = 0.35 + 0.65 * abs(dot(normal, light));
+    return vec4<f32>(in.color.rgb * shade, in.color.a);
+}
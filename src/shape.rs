@@ -0,0 +1,143 @@
+// What the boids look like when drawn as meshes. Every shape is a triangle list in the
+// boid's own frame, pointing along +x with its nose at x = 1, and `shader.wgsl` rotates
+// it to the boid's heading and scales it by the boid's size. The third component is
+// the homogeneous coordinate and always 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoidShape {
+    Triangle,
+    Arrow,
+    Chevron,
+    Dart,
+    Circle,
+}
+
+const TRIANGLE: &[[f32; 3]] = &[
+    [ 1.00000,  0.00000, 1.0],
+    [-0.83147,  0.55557, 1.0],
+    [-0.83147, -0.55557, 1.0],
+];
+
+const ARROW: &[[f32; 3]] = &[
+    // head
+    [ 1.0,  0.0, 1.0],
+    [ 0.2,  0.6, 1.0],
+    [ 0.2, -0.6, 1.0],
+    // shaft
+    [-1.0, -0.2, 1.0],
+    [ 0.2, -0.2, 1.0],
+    [ 0.2,  0.2, 1.0],
+    [-1.0, -0.2, 1.0],
+    [ 0.2,  0.2, 1.0],
+    [-1.0,  0.2, 1.0],
+];
+
+const CHEVRON: &[[f32; 3]] = &[
+    [ 1.0,  0.0, 1.0],
+    [-0.8,  0.7, 1.0],
+    [-0.3,  0.0, 1.0],
+    [ 1.0,  0.0, 1.0],
+    [-0.3,  0.0, 1.0],
+    [-0.8, -0.7, 1.0],
+];
+
+const DART: &[[f32; 3]] = &[
+    [ 1.0,  0.0, 1.0],
+    [-0.7,  0.3, 1.0],
+    [-1.0,  0.0, 1.0],
+    [ 1.0,  0.0, 1.0],
+    [-1.0,  0.0, 1.0],
+    [-0.7, -0.3, 1.0],
+];
+
+// Segments in the circle's triangle fan.
+const CIRCLE_SEGMENTS: usize = 16;
+const CIRCLE_RADIUS: f32 = 0.6;
+
+impl BoidShape {
+    pub const ALL: [BoidShape; 5] = [
+        BoidShape::Triangle,
+        BoidShape::Arrow,
+        BoidShape::Chevron,
+        BoidShape::Dart,
+        BoidShape::Circle,
+    ];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoidShape::Triangle => "triangle",
+            BoidShape::Arrow => "arrow",
+            BoidShape::Chevron => "chevron",
+            BoidShape::Dart => "dart",
+            BoidShape::Circle => "circle",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    pub fn vertices(self) -> Vec<[f32; 3]> {
+        match self {
+            BoidShape::Triangle => TRIANGLE.to_vec(),
+            BoidShape::Arrow => ARROW.to_vec(),
+            BoidShape::Chevron => CHEVRON.to_vec(),
+            BoidShape::Dart => DART.to_vec(),
+            BoidShape::Circle => circle(),
+        }
+    }
+}
+
+// A disc with a notch sticking out the front so the heading is still visible.
+fn circle() -> Vec<[f32; 3]> {
+    let mut vertices = Vec::new();
+    for i in 0..CIRCLE_SEGMENTS {
+        let a0 = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+        let a1 = (i + 1) as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+        vertices.push([0.0, 0.0, 1.0]);
+        vertices.push([a0.cos() * CIRCLE_RADIUS, a0.sin() * CIRCLE_RADIUS, 1.0]);
+        vertices.push([a1.cos() * CIRCLE_RADIUS, a1.sin() * CIRCLE_RADIUS, 1.0]);
+    }
+    vertices.extend_from_slice(&[
+        [1.0,  0.00, 1.0],
+        [0.4,  0.25, 1.0],
+        [0.4, -0.25, 1.0],
+    ]);
+    vertices
+}
+
+// Loads a mesh from a text file with one `x y` vertex per line, every three vertices
+// making a triangle. Blank lines and anything after a `#` are ignored. Clockwise
+// triangles are flipped, since the boid pipelines cull back faces.
+pub fn load_mesh(path: &str) -> anyhow::Result<Vec<[f32; 3]>> {
+    let source = std::fs::read_to_string(path)?;
+    let mut vertices = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() { continue; }
+
+        let coords = line.split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| anyhow::anyhow!("line {}: {err}", i + 1))?;
+        let [x, y] = coords[..] else {
+            anyhow::bail!("line {}: expected `x y`, found {} numbers", i + 1, coords.len());
+        };
+        vertices.push([x, y, 1.0]);
+    }
+
+    if vertices.is_empty() || vertices.len() % 3 != 0 {
+        anyhow::bail!("expected a whole number of triangles, found {} vertices", vertices.len());
+    }
+    for triangle in vertices.chunks_exact_mut(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+        let cross = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        if cross < 0.0 {
+            triangle.swap(1, 2);
+        }
+    }
+    Ok(vertices)
+}
@@ -0,0 +1,472 @@
+use winit::event::WindowEvent;
+
+use wgpu::util::DeviceExt;
+
+use rand::prelude::*;
+
+use crate::orbit_camera::OrbitCamera;
+use crate::boid::{Boid3D, BoidTraits, BoidStats};
+use crate::params::SimParams;
+use crate::simulation::Simulation;
+use crate::spawner;
+
+// The 3D counterpart of the 2D pipelines in `Renderer`. It shares the device, surface,
+// frame clock, per-boid traits and the scenario's goals, flow field and population with
+// the 2D `Simulation`, and runs the same kernel, but owns its own boid state, camera,
+// depth buffer and pipelines. Cursor goals stay at the origin, as the cursor isn't
+// anywhere in 3D.
+pub struct Sim3D {
+    boids_buffers: Vec<wgpu::Buffer>,
+    boids_bind_groups: Vec<wgpu::BindGroup>,
+    params_buffer: wgpu::Buffer,
+
+    camera: OrbitCamera,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+
+    depth_view: wgpu::TextureView,
+
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    n_boids: u32,
+    // only the first boids are alive, as in 2D, and there are no emitters or sinks to
+    // change how many
+    population: u32,
+
+    render_pipeline: wgpu::RenderPipeline,
+    compute_pipeline: wgpu::ComputePipeline,
+}
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// Half the side of the cube the boids start in. The flock radius is the same as in 2D,
+// so the volume is kept small enough that boids still find neighbours.
+const SPAWN_EXTENT: f32 = 96.0;
+
+const CONE_SEGMENTS: usize = 6;
+
+impl Sim3D {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        rng: &mut impl Rng,
+        simulation: &Simulation,
+        params: SimParams,
+    ) -> Self {
+        let Simulation { boids_bind_group_layout, traits_buffer, interactions_buffer, .. } = simulation;
+        let n_boids = (traits_buffer.size() / std::mem::size_of::<BoidTraits>() as u64) as usize;
+
+        let mut boids = Vec::new();
+        for _ in 0..n_boids {
+            let pos = [(); 3].map(|_| SPAWN_EXTENT * (2.0 * rng.random::<f32>() - 1.0));
+            let z = 2.0 * rng.random::<f32>() - 1.0;
+            let (sa, ca) = f32::sin_cos(rng.random::<f32>() * std::f32::consts::TAU);
+            let r = f32::sqrt(1.0 - z * z);
+            boids.push(Boid3D::new(pos, [r * ca, r * sa, z]));
+        }
+
+        let mut boids_buffers = Vec::new();
+        for i in 0..2 {
+            let buffer = device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some(format!("Boids 3D Buffer {}", i).as_str()),
+                    contents: bytemuck::cast_slice(&boids),
+                    usage: wgpu::BufferUsages::VERTEX
+                        | wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_DST,
+                }
+            );
+            boids_buffers.push(buffer);
+        }
+
+        let stats_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Boid Stats 3D Buffer"),
+                contents: bytemuck::cast_slice(&vec![BoidStats::default(); n_boids]),
+                usage: wgpu::BufferUsages::STORAGE,
+            }
+        );
+
+        let params = with_3d_bounds(params);
+        let params_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Sim Params 3D Buffer"),
+                contents: bytemuck::cast_slice(&[params]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        // the storage layout is identical to the 2D one, only the element type differs
+        let mut boids_bind_groups = Vec::new();
+        for i in 0..2 {
+            let bind_group = device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    label: Some(format!("Bind Group 3D {}", i).as_str()),
+                    layout: boids_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: boids_buffers[i % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: boids_buffers[(i + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: traits_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: params_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: interactions_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: stats_buffer.as_entire_binding(),
+                        },
+                    ],
+                }
+            );
+            boids_bind_groups.push(bind_group);
+        }
+
+
+        let camera = OrbitCamera::new(winit::dpi::PhysicalSize::new(config.width, config.height));
+        let camera_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Orbit Camera Buffer"),
+                contents: bytemuck::cast_slice(&[camera.to_matrix()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let camera_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Orbit Camera Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }
+                ]
+            }
+        );
+        let camera_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Orbit Camera Bind Group"),
+                layout: &camera_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: camera_buffer.as_entire_binding(),
+                    }
+                ]
+            }
+        );
+
+
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline 3D Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let render_shader = device.create_shader_module(wgpu::include_wgsl!("shader3d.wgsl"));
+
+        let render_pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Render Pipeline 3D"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &render_shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                        },
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<Boid3D>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &[
+                                wgpu::VertexAttribute {
+                                    offset: 0,
+                                    shader_location: 1,
+                                    format: wgpu::VertexFormat::Float32x3,
+                                },
+                                wgpu::VertexAttribute {
+                                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                                    shader_location: 2,
+                                    format: wgpu::VertexFormat::Float32x3,
+                                },
+                            ],
+                        },
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<BoidTraits>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &[
+                                wgpu::VertexAttribute {
+                                    offset: 0,
+                                    shader_location: 3,
+                                    format: wgpu::VertexFormat::Float32x4,
+                                },
+                                wgpu::VertexAttribute {
+                                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                                    shader_location: 4,
+                                    format: wgpu::VertexFormat::Float32,
+                                },
+                            ],
+                        },
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &render_shader,
+                    entry_point: "fs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[
+                        Some(wgpu::ColorTargetState {
+                            format: config.format,
+                            blend: Some(wgpu::BlendState::REPLACE),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }),
+                    ],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    // the cone is closed, but it's cheap enough to not care about winding
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            }
+        );
+
+        let compute_shader = device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("compute3d.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(include_str!("compute3d.wgsl"), include_str!("compute.wgsl")).into()),
+            }
+        );
+
+        let compute_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipeline 3D Layout"),
+                bind_group_layouts: &[
+                    boids_bind_group_layout,
+                    &simulation.goals_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let compute_pipeline = device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipeline 3D"),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point: "cs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            }
+        );
+
+
+        let vertices = cone_mesh();
+        let vertex_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Vertex 3D Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }
+        );
+
+        let depth_view = create_depth_view(device, config);
+
+        Self {
+            boids_buffers,
+            boids_bind_groups,
+            params_buffer,
+
+            camera,
+            camera_buffer,
+            camera_bind_group,
+
+            depth_view,
+
+            vertex_buffer,
+            vertex_count: vertices.len() as u32,
+            n_boids: n_boids as u32,
+            population: simulation.initial_population,
+
+            render_pipeline,
+            compute_pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.depth_view = create_depth_view(device, config);
+        self.camera.update_aspect(winit::dpi::PhysicalSize::new(config.width, config.height));
+    }
+
+    pub fn set_params(&self, queue: &wgpu::Queue, params: SimParams) {
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[with_3d_bounds(params)]));
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        self.camera.process_events(event)
+    }
+
+    // `goals_bind_group` is the `Simulation`'s, whose layout the pipeline was made with.
+    pub fn update(&self, device: &wgpu::Device, queue: &wgpu::Queue, goals_bind_group: &wgpu::BindGroup, frame_count: usize) {
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera.to_matrix()]));
+
+        let mut compute_encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Pass 3D Encoder")
+            }
+        );
+
+        let mut compute_pass = compute_encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass 3D"),
+                timestamp_writes: None,
+            }
+        );
+
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.boids_bind_groups[frame_count % 2], &[]);
+        compute_pass.set_bind_group(1, goals_bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.n_boids.div_ceil(64), 1, 1);
+
+        drop(compute_pass);
+
+        queue.submit(std::iter::once(compute_encoder.finish()));
+    }
+
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        traits_buffer: &wgpu::Buffer,
+        frame_count: usize,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("Render Pass 3D"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(crate::CLEAR_COLOR),
+                            store: wgpu::StoreOp::Store,
+                        }
+                    })
+                ],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            }
+        );
+
+        let instance_buffer = &self.boids_buffers[(frame_count + 1) % 2];
+
+        render_pass.set_pipeline(&self.render_pipeline);
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_vertex_buffer(2, traits_buffer.slice(..));
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+
+        render_pass.draw(0..self.vertex_count, 0..self.population);
+    }
+}
+
+// The 2D wall is sized for a 1024x1024 spawn area, which is far too roomy in 3D, so it's
+// scaled to the 3D spawn cube, putting the default radius at twice its extent. It still
+// follows the slider and the scenario, and the flow field stretches with it.
+fn with_3d_bounds(mut params: SimParams) -> SimParams {
+    params.wall_radius *= 2.0 * SPAWN_EXTENT / spawner::EXTENT;
+    params
+}
+
+fn create_depth_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::TextureView {
+    let texture = device.create_texture(
+        &wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// A closed cone pointing down +x, as a plain triangle list.
+fn cone_mesh() -> Vec<[f32; 3]> {
+    let tip = [1.0, 0.0, 0.0];
+    let base = -0.8;
+    let radius = 0.45;
+    let rim: Vec<[f32; 3]> = (0..CONE_SEGMENTS)
+        .map(|i| {
+            let a = i as f32 / CONE_SEGMENTS as f32 * std::f32::consts::TAU;
+            [base, radius * a.cos(), radius * a.sin()]
+        })
+        .collect();
+
+    let mut vertices = Vec::new();
+    for i in 0..CONE_SEGMENTS {
+        let (a, b) = (rim[i], rim[(i + 1) % CONE_SEGMENTS]);
+        vertices.extend_from_slice(&[tip, a, b]);
+        vertices.extend_from_slice(&[[base, 0.0, 0.0], b, a]);
+    }
+    vertices
+}
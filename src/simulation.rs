@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::boid::{Boid, BoidTraits, BoidStats, InteractionMatrix};
+use crate::params::SimParams;
+use crate::scenario::{self, Scenario, GoalUniform};
+use crate::flow_field::FlowField;
+use crate::spawning::Spawning;
+use crate::error::Error;
+
+// How `cs_main` finds each boid's neighbours. Both are brute force, checking every pair
+// of boids so the cost grows with the square of the count, and give the same result.
+// They differ only in how the boids are read, and there's no spatial binning yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeighbourSearch {
+    // every invocation reads every boid from the storage buffer
+    BruteForce,
+    // each workgroup loads the boids a tile at a time into workgroup memory and reads
+    // them from there, which saves on memory traffic but not on the pairs checked
+    SharedMemory,
+}
+
+impl NeighbourSearch {
+    pub const ALL: [NeighbourSearch; 2] = [
+        NeighbourSearch::BruteForce,
+        NeighbourSearch::SharedMemory,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NeighbourSearch::BruteForce => "brute-force",
+            NeighbourSearch::SharedMemory => "shared-memory",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+// The workgroup size of `cs_main`.
+const WORKGROUP_SIZE: u32 = 64;
+
+// The 2D boids and everything `cs_main` needs to step them, without anything to show
+// them with. `Renderer` draws from these buffers, and the benchmark runs them headless.
+pub struct Simulation {
+    pub capacity: usize,
+    pub initial_population: u32,
+
+    pub boids_buffers: Vec<wgpu::Buffer>,
+    pub traits_buffer: wgpu::Buffer,
+    pub stats_buffer: wgpu::Buffer,
+    pub interactions_buffer: wgpu::Buffer,
+    pub population_buffer: wgpu::Buffer,
+    pub params_buffer: wgpu::Buffer,
+    pub boids_bind_group_layout: wgpu::BindGroupLayout,
+    boids_bind_groups: Vec<wgpu::BindGroup>,
+
+    goals: Vec<GoalUniform>,
+    goals_buffer: wgpu::Buffer,
+    // the goals, the flow field and the population, which the 3D mode shares
+    pub goals_bind_group_layout: wgpu::BindGroupLayout,
+    pub goals_bind_group: wgpu::BindGroup,
+    pub flow_field: FlowField,
+
+    // adds and removes boids, when the scenario has emitters or sinks
+    spawning: Option<Spawning>,
+    compute_pipeline: wgpu::ComputePipeline,
+}
+
+impl Simulation {
+    // There's room for as many boids as are given, and the scenario's population says
+    // how many of them start alive.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scenario: &Scenario,
+        params: SimParams,
+        interactions: &InteractionMatrix,
+        boids: &[Boid],
+        traits: &[BoidTraits],
+        seed: u64,
+        search: NeighbourSearch,
+    ) -> anyhow::Result<Self> {
+        let capacity = boids.len();
+
+        let mut boids_buffers = Vec::new();
+        for i in 0..2 {
+            let buffer = device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some(format!("Boids Buffer {}", i).as_str()),
+                    contents: bytemuck::cast_slice(boids),
+                    usage: wgpu::BufferUsages::VERTEX
+                        | wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_SRC
+                        | wgpu::BufferUsages::COPY_DST,
+                }
+            );
+            boids_buffers.push(buffer);
+        }
+
+        let traits_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Boid Traits Buffer"),
+                contents: bytemuck::cast_slice(traits),
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let stats_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Boid Stats Buffer"),
+                contents: bytemuck::cast_slice(&vec![BoidStats::default(); capacity]),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+            }
+        );
+
+        let interactions_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Interactions Buffer"),
+                contents: bytemuck::cast_slice(interactions.entries()),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let params_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Sim Params Buffer"),
+                contents: bytemuck::cast_slice(&[params]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let boids_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Boid Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }
+        );
+
+        let mut boids_bind_groups = Vec::new();
+        for i in 0..2 {
+            let bind_group = device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    label: Some(format!("Bind Group {}", i).as_str()),
+                    layout: &boids_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: boids_buffers[i % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: boids_buffers[(i + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: traits_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: params_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: interactions_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: stats_buffer.as_entire_binding(),
+                        },
+                    ],
+                }
+            );
+            boids_bind_groups.push(bind_group);
+        }
+
+
+        let (goals, waypoints) = scenario::goal_buffers(&scenario.goals);
+        let goals_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Goals Buffer"),
+                contents: bytemuck::cast_slice(&goals),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let waypoints_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Waypoints Buffer"),
+                contents: bytemuck::cast_slice(&waypoints),
+                usage: wgpu::BufferUsages::STORAGE,
+            }
+        );
+
+        let flow_field = match &scenario.flow_field {
+            Some(spec) => spec.build(seed)
+                .map_err(|err| anyhow::anyhow!("failed to build flow field: {err}"))?,
+            None => FlowField::still(),
+        };
+        let flow_field_view = flow_field.create_texture(device, queue)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Boids past the live count are dead and skipped. The count only ever changes on
+        // the GPU, so nothing drawn or simulated depends on knowing it on the CPU.
+        let initial_population = match scenario.population {
+            Some(population) if population as usize > capacity => {
+                log::warn!("a population of {population} is more than the {capacity} boids there's room for");
+                capacity as u32
+            }
+            Some(population) => population,
+            None => capacity as u32,
+        };
+        let population_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Population Buffer"),
+                contents: bytemuck::cast_slice(&[initial_population]),
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let goals_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Goals Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }
+        );
+        let goals_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Goals Bind Group"),
+                layout: &goals_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: goals_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: waypoints_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&flow_field_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: population_buffer.as_entire_binding(),
+                    },
+                ],
+            }
+        );
+
+        let compute_shader = device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("compute.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(include_str!("compute2d.wgsl"), include_str!("compute.wgsl")).into()),
+            }
+        );
+
+        let compute_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipeline Layout"),
+                bind_group_layouts: &[
+                    &boids_bind_group_layout,
+                    &goals_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let constants = HashMap::from([
+            ("SHARED_MEMORY".to_string(), (search == NeighbourSearch::SharedMemory) as u32 as f64),
+        ]);
+        let compute_pipeline = device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipeline"),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point: "cs_main",
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &constants,
+                    ..Default::default()
+                },
+                cache: None,
+            }
+        );
+
+        let spawning = Spawning::new(
+            device,
+            &scenario.emitters,
+            &scenario.sinks,
+            seed as u32,
+            &boids_buffers,
+            &traits_buffer,
+            &population_buffer,
+        );
+
+        Ok(Self {
+            capacity,
+            initial_population,
+
+            boids_buffers,
+            traits_buffer,
+            stats_buffer,
+            interactions_buffer,
+            population_buffer,
+            params_buffer,
+            boids_bind_group_layout,
+            boids_bind_groups,
+
+            goals,
+            goals_buffer,
+            goals_bind_group_layout,
+            goals_bind_group,
+            flow_field,
+
+            spawning,
+            compute_pipeline,
+        })
+    }
+
+    pub fn set_params(&self, queue: &wgpu::Queue, params: SimParams) {
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+
+    // Moves the scenario's cursor goals to `cursor`, if it has any.
+    pub fn set_cursor(&mut self, queue: &wgpu::Queue, cursor: [f32; 2]) {
+        if self.goals.is_empty() { return; }
+        for goal in self.goals.iter_mut().filter(|g| g.kind == scenario::GOAL_CURSOR) {
+            goal.point = cursor;
+        }
+        queue.write_buffer(&self.goals_buffer, 0, bytemuck::cast_slice(&self.goals));
+    }
+
+    // Records one step, which reads `boids_buffers[frame % 2]` and leaves the boids in
+    // the other buffer.
+    pub fn step(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: usize,
+        dt: f32,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        let mut compute_pass = encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass"),
+                timestamp_writes,
+            }
+        );
+
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.boids_bind_groups[frame % 2], &[]);
+        compute_pass.set_bind_group(1, &self.goals_bind_group, &[]);
+        compute_pass.dispatch_workgroups((self.capacity as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+
+        drop(compute_pass);
+
+        if let Some(spawning) = &mut self.spawning {
+            spawning.step(queue, encoder, &self.boids_buffers, &self.traits_buffer, frame, dt);
+        }
+    }
+
+    // Replaces every boid, as at the start of a run.
+    pub fn reset(&mut self, queue: &wgpu::Queue, boids: &[Boid], traits: &[BoidTraits]) {
+        for buffer in &self.boids_buffers {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(boids));
+        }
+        queue.write_buffer(&self.traits_buffer, 0, bytemuck::cast_slice(traits));
+        queue.write_buffer(&self.population_buffer, 0, bytemuck::cast_slice(&[self.initial_population]));
+        if let Some(spawning) = &mut self.spawning {
+            spawning.reset();
+        }
+    }
+}
+
+// A device for running simulations without a window, with whichever of `features` the
+// adapter has. `WGPU_BACKEND` picks the backend, for comparing them.
+pub async fn headless_device(features: wgpu::Features) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY),
+        ..Default::default()
+    });
+    let adapter = instance.request_adapter(
+        &wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }
+    ).await.ok_or(Error::NoAdapter)?;
+    log::info!("running headless on {:?}", adapter.get_info());
+
+    let (device, queue) = adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            required_features: adapter.features() & features,
+            required_limits: wgpu::Limits::default(),
+            label: None,
+            memory_hints: Default::default(),
+        },
+        None,
+    ).await.map_err(|err| Error::RequestDevice(err.to_string()))?;
+    Ok((device, queue))
+}
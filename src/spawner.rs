@@ -0,0 +1,104 @@
+use rand::prelude::*;
+
+use crate::boid::{self, Boid, BoidTraits};
+
+// How the boids are laid out at the start of a run and when it's reset. Every boid
+// gets a random heading at unit speed and the traits of a random entry of
+// `boid::SPECIES`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spawner {
+    Square,
+    Disc,
+    Ring,
+    Clusters,
+}
+
+// Half the side of the square, and the radius of the disc.
+pub const EXTENT: f32 = 512.0;
+const RING_RADII: [f32; 2] = [320.0, 420.0];
+const CLUSTERS: usize = 8;
+const CLUSTER_RADIUS: f32 = 48.0;
+
+impl Spawner {
+    pub const ALL: [Spawner; 4] = [
+        Spawner::Square,
+        Spawner::Disc,
+        Spawner::Ring,
+        Spawner::Clusters,
+    ];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Spawner::Square => "square",
+            Spawner::Disc => "disc",
+            Spawner::Ring => "ring",
+            Spawner::Clusters => "clusters",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    pub fn spawn(self, rng: &mut impl Rng, count: usize) -> (Vec<Boid>, Vec<BoidTraits>) {
+        // only drawn when needed, so a seed gives the same square as it always has
+        let centres: Vec<[f32; 2]> = match self {
+            Spawner::Clusters => (0..CLUSTERS)
+                .map(|_| [uniform(rng, EXTENT * 0.75), uniform(rng, EXTENT * 0.75)])
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut boids = Vec::with_capacity(count);
+        let mut traits = Vec::with_capacity(count);
+        for _ in 0..count {
+            let [x, y] = match self {
+                Spawner::Square => [uniform(rng, EXTENT), uniform(rng, EXTENT)],
+                Spawner::Disc => {
+                    let radius = EXTENT * rng.random::<f32>().sqrt();
+                    polar(rng, radius)
+                }
+                Spawner::Ring => {
+                    let [inner, outer] = RING_RADII;
+                    let radius = inner + (outer - inner) * rng.random::<f32>();
+                    polar(rng, radius)
+                }
+                Spawner::Clusters => {
+                    let [cx, cy] = *centres.choose(rng).unwrap();
+                    let radius = CLUSTER_RADIUS * rng.random::<f32>().sqrt();
+                    let [x, y] = polar(rng, radius);
+                    [cx + x, cy + y]
+                }
+            };
+            let [vx, vy] = random_heading(rng);
+            boids.push(Boid::new(x, y, vx, vy));
+            traits.push(random_species(rng));
+        }
+        (boids, traits)
+    }
+}
+
+// A unit velocity in a random direction.
+pub fn random_heading(rng: &mut impl Rng) -> [f32; 2] {
+    let a = rng.random::<f32>() * std::f32::consts::TAU;
+    let (vy, vx) = f32::sin_cos(a);
+    [vx, vy]
+}
+
+pub fn random_species(rng: &mut impl Rng) -> BoidTraits {
+    *boid::SPECIES.choose(rng).unwrap()
+}
+
+// Uniform in `-extent..extent`.
+fn uniform(rng: &mut impl Rng, extent: f32) -> f32 {
+    2.0 * extent * rng.random::<f32>() - extent
+}
+
+fn polar(rng: &mut impl Rng, radius: f32) -> [f32; 2] {
+    let a = rng.random::<f32>() * std::f32::consts::TAU;
+    [radius * a.cos(), radius * a.sin()]
+}
@@ -0,0 +1,262 @@
+use wgpu::util::DeviceExt;
+
+use crate::boid::Boid;
+use crate::scenario::{self, Emitter, EmitterUniform, Sink};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpawnParams {
+    seed: u32,
+    frame: u32,
+    spawn_count: u32,
+    capacity: u32,
+}
+
+// Adds and removes boids on the GPU for a scenario's emitters and sinks. Once a frame's
+// simulation step is done, `cs_population` drops the boids that reached a sink and
+// appends the frame's new ones, writing into the buffer the step read from, which is
+// then copied back over the step's output. Stats aren't moved along with the boids, so
+// they're off by a few places until the next step recomputes them, and the trail of a
+// boid that moved can briefly join up with another boid's.
+pub struct Spawning {
+    capacity: u32,
+    seed: u32,
+    rates: Vec<f32>,
+    // Fractional boids owed by each emitter, carried over from earlier frames.
+    owed: Vec<f32>,
+    emitters: Vec<EmitterUniform>,
+
+    emitters_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    scratch_traits_buffer: wgpu::Buffer,
+
+    bind_groups: Vec<wgpu::BindGroup>,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Spawning {
+    // Returns `None` when there's nothing to spawn or remove, so the pass can be skipped.
+    pub fn new(
+        device: &wgpu::Device,
+        emitters: &[Emitter],
+        sinks: &[Sink],
+        seed: u32,
+        boids_buffers: &[wgpu::Buffer],
+        traits_buffer: &wgpu::Buffer,
+        population_buffer: &wgpu::Buffer,
+    ) -> Option<Self> {
+        if emitters.is_empty() && sinks.is_empty() {
+            return None;
+        }
+        let capacity = (boids_buffers[0].size() / std::mem::size_of::<Boid>() as u64) as u32;
+        let (emitter_uniforms, sink_uniforms, palette) = scenario::population_buffers(emitters, sinks);
+
+        let emitters_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Emitters Buffer"),
+                contents: bytemuck::cast_slice(&emitter_uniforms),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let sinks_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Sinks Buffer"),
+                contents: bytemuck::cast_slice(&sink_uniforms),
+                usage: wgpu::BufferUsages::STORAGE,
+            }
+        );
+        let palette_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Spawn Palette Buffer"),
+                contents: bytemuck::cast_slice(&palette),
+                usage: wgpu::BufferUsages::STORAGE,
+            }
+        );
+        let params_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Spawn Params Buffer"),
+                size: std::mem::size_of::<SpawnParams>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+        let scratch_traits_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Scratch Traits Buffer"),
+                size: traits_buffer.size(),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }
+        );
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Population Bind Group Layout"),
+                entries: &[
+                    storage_entry(0, true),
+                    storage_entry(1, false),
+                    storage_entry(2, true),
+                    storage_entry(3, false),
+                    storage_entry(4, false),
+                    storage_entry(5, true),
+                    storage_entry(6, true),
+                    storage_entry(7, true),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 8,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }
+        );
+
+        // Bind group `i` is for the step that read `boids_buffers[i]` and wrote the other.
+        let bind_groups = (0..boids_buffers.len())
+            .map(|i| device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    label: Some(format!("Population Bind Group {}", i).as_str()),
+                    layout: &bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: boids_buffers[(i + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: boids_buffers[i].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: traits_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: scratch_traits_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: population_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: emitters_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 6,
+                            resource: sinks_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 7,
+                            resource: palette_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 8,
+                            resource: params_buffer.as_entire_binding(),
+                        },
+                    ],
+                }
+            ))
+            .collect();
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("population.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Population Pipeline Layout"),
+                bind_group_layouts: &[
+                    &bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+        let pipeline = device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("Population Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "cs_population",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            }
+        );
+
+        Some(Self {
+            capacity,
+            seed,
+            rates: emitters.iter().map(|e| e.rate).collect(),
+            owed: vec![0.0; emitters.len()],
+            emitters: emitter_uniforms,
+
+            emitters_buffer,
+            params_buffer,
+            scratch_traits_buffer,
+
+            bind_groups,
+            pipeline,
+        })
+    }
+
+    pub fn reset(&mut self) {
+        self.owed.fill(0.0);
+    }
+
+    // Runs after the simulation step that read `boids_buffers[frame % 2]`, `dt` after the
+    // last one.
+    pub fn step(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        boids_buffers: &[wgpu::Buffer],
+        traits_buffer: &wgpu::Buffer,
+        frame: usize,
+        dt: f32,
+    ) {
+        let mut spawn_count = 0;
+        for ((emitter, owed), rate) in self.emitters.iter_mut().zip(&mut self.owed).zip(&self.rates) {
+            *owed += rate * dt;
+            let count = owed.floor();
+            *owed -= count;
+            emitter.first_spawn = spawn_count;
+            emitter.spawn_count = count as u32;
+            spawn_count += count as u32;
+        }
+        queue.write_buffer(&self.emitters_buffer, 0, bytemuck::cast_slice(&self.emitters));
+
+        let params = SpawnParams {
+            seed: self.seed,
+            frame: frame as u32,
+            spawn_count,
+            capacity: self.capacity,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+
+        let mut compute_pass = encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("Population Pass"),
+                timestamp_writes: None,
+            }
+        );
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_groups[frame % 2], &[]);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+        drop(compute_pass);
+
+        let (scratch, output) = (&boids_buffers[frame % 2], &boids_buffers[(frame + 1) % 2]);
+        encoder.copy_buffer_to_buffer(scratch, 0, output, 0, output.size());
+        encoder.copy_buffer_to_buffer(&self.scratch_traits_buffer, 0, traits_buffer, 0, traits_buffer.size());
+    }
+}
@@ -0,0 +1,29 @@
+// How the 2D boids are drawn. Triangles draws the boid mesh and is the cheapest, sprites
+// are quads shaded from a signed distance field so their edges stay smooth at any zoom,
+// and textured quads show the image passed with `--sprite`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoidStyle {
+    Triangles,
+    Sprites,
+    Texture,
+}
+
+impl BoidStyle {
+    pub const ALL: [BoidStyle; 3] = [BoidStyle::Triangles, BoidStyle::Sprites, BoidStyle::Texture];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoidStyle::Triangles => "triangles",
+            BoidStyle::Sprites => "sprites",
+            BoidStyle::Texture => "texture",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use serde::Deserialize;
+use rand::prelude::*;
+
+use crate::options::Options;
+use crate::params::SimParams;
+use crate::scenario::Scenario;
+use crate::simulation::{self, Simulation, NeighbourSearch};
+use crate::spawner::Spawner;
+use crate::analytics::{Analytics, FlockMetrics};
+
+// Sweeps over the number of boids as well as the `SimParams`.
+const COUNT: &str = "count";
+
+// Headless runs over combinations of parameters, each for `steps` steps, with the flock
+// measured at the end of every run. Loaded from JSON with `--sweep=<path>`, e.g.
+//
+//     { "steps": 500, "seeds": [1, 2, 3],
+//       "parameters": { "cohesion_weight": [0.1, 0.25, 0.5],
+//                       "alignment_weight": { "min": 0, "max": 2, "steps": 5 } } }
+//
+// Every combination of values is run with every seed, unless `samples` is given, in which
+// case that many combinations are drawn at random instead.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SweepSpec {
+    pub steps: u32,
+    #[serde(default = "SweepSpec::default_seeds")]
+    pub seeds: Vec<u64>,
+    #[serde(default)]
+    pub samples: Option<usize>,
+    // Any of `SimParams::NAMES`, or `count` for the number of boids. Sorted by name, which
+    // is also the order of the report's columns.
+    pub parameters: BTreeMap<String, Values>,
+    // Used instead of `--scenario` if it's given.
+    #[serde(default)]
+    pub scenario: Option<String>,
+    // Where the CSV report is written.
+    #[serde(default = "SweepSpec::default_output")]
+    pub output: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Values {
+    List(Vec<f32>),
+    // `steps` evenly spaced values from `min` to `max`, both included. A random sample
+    // is drawn from anywhere between them.
+    Range { min: f32, max: f32, steps: usize },
+}
+
+impl Values {
+    fn grid(&self) -> Vec<f32> {
+        match *self {
+            Values::List(ref values) => values.clone(),
+            Values::Range { min, steps: 1, .. } => vec![min],
+            Values::Range { min, max, steps } => (0..steps)
+                .map(|i| min + (max - min) * i as f32 / (steps - 1) as f32)
+                .collect(),
+        }
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            Values::List(ref values) => *values.choose(rng).unwrap(),
+            Values::Range { min, max, .. } => min + (max - min) * rng.random::<f32>(),
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        match *self {
+            Values::List(ref values) => anyhow::ensure!(!values.is_empty(), "a list of values can't be empty"),
+            Values::Range { min, max, steps } => {
+                anyhow::ensure!(steps > 0, "a range needs at least one step");
+                anyhow::ensure!(min <= max, "a range's min can't be more than its max");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SweepSpec {
+    fn default_seeds() -> Vec<u64> {
+        vec![0]
+    }
+
+    fn default_output() -> String {
+        "sweep.csv".to_string()
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let spec: Self = serde_json::from_reader(std::io::BufReader::new(file))?;
+        anyhow::ensure!(spec.steps > 0, "a sweep needs at least one step per run");
+        anyhow::ensure!(!spec.seeds.is_empty(), "a sweep needs at least one seed");
+        for (name, values) in &spec.parameters {
+            anyhow::ensure!(
+                name == COUNT || SimParams::default().get_mut(name).is_some(),
+                "unknown parameter {name:?}, expected {COUNT} or one of {:?}",
+                SimParams::NAMES,
+            );
+            values.check().map_err(|err| anyhow::anyhow!("{name}: {err}"))?;
+            if name == COUNT {
+                let counts = values.grid();
+                anyhow::ensure!(counts.iter().all(|&c| c >= 1.0), "{COUNT}: there has to be at least one boid");
+            }
+        }
+        Ok(spec)
+    }
+
+    // The values of the parameters for every run but the seed, in the order of
+    // `parameters`.
+    fn combinations(&self, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+        if let Some(samples) = self.samples {
+            return (0..samples)
+                .map(|_| self.parameters.values().map(|v| v.sample(rng)).collect())
+                .collect();
+        }
+        let mut combinations = vec![Vec::new()];
+        for values in self.parameters.values() {
+            combinations = combinations.into_iter()
+                .flat_map(|combination| values.grid().into_iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push(value);
+                    combination
+                }))
+                .collect();
+        }
+        combinations
+    }
+}
+
+// Runs the sweep in `path` one run after another on a single device, writing a row of
+// the report as each run finishes.
+pub async fn run(options: &Options, path: &str) -> anyhow::Result<()> {
+    let spec = SweepSpec::load(path)?;
+    let scenario = match spec.scenario.as_ref().or(options.scenario.as_ref()) {
+        Some(path) => Scenario::load(path)
+            .map_err(|err| anyhow::anyhow!("failed to load scenario {path:?}: {err}"))?,
+        None => Scenario::default(),
+    };
+    let (device, queue) = simulation::headless_device(wgpu::Features::empty()).await?;
+
+    let interactions = scenario.interactions();
+    let spawner = options.spawner.unwrap_or(Spawner::Square);
+    let search = options.search.unwrap_or(NeighbourSearch::BruteForce);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(options.seed.unwrap_or(0));
+    let combinations = spec.combinations(&mut rng);
+
+    let mut report = BufWriter::new(File::create(&spec.output)?);
+    let names: Vec<&str> = spec.parameters.keys().map(String::as_str).collect();
+    writeln!(report, "combination,seed,{},steps,{}", names.join(","), FlockMetrics::CSV_COLUMNS)?;
+
+    let runs = combinations.len() * spec.seeds.len();
+    for (i, values) in combinations.iter().enumerate() {
+        for (j, &seed) in spec.seeds.iter().enumerate() {
+            let mut params = crate::sim_params(options, &scenario, &interactions, seed);
+            let mut count = crate::N_BOIDS;
+            for (&name, &value) in names.iter().zip(values) {
+                match params.get_mut(name) {
+                    Some(param) => *param = value,
+                    None => count = value.round() as usize,
+                }
+            }
+
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let (boids, traits) = spawner.spawn(&mut rng, count);
+            let mut simulation = Simulation::new(&device, &queue, &scenario, params, &interactions, &boids, &traits, seed, search)?;
+            let metrics = run_steps(&device, &queue, &mut simulation, params, spec.steps)?;
+
+            let values: Vec<String> = values.iter().map(f32::to_string).collect();
+            writeln!(report, "{i},{seed},{},{},{}", values.join(","), spec.steps, metrics.csv_fields())?;
+            report.flush()?;
+            log::info!("run {}/{runs}: {names:?} = {values:?}, seed {seed}: {metrics:?}", i * spec.seeds.len() + j + 1);
+        }
+    }
+    log::info!("wrote sweep report to {:?}", spec.output);
+    Ok(())
+}
+
+// Steps the simulation the way `Renderer` does, one submission per step so each sees
+// its own frame number, then measures the flock.
+fn run_steps(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    simulation: &mut Simulation,
+    mut params: SimParams,
+    steps: u32,
+) -> anyhow::Result<FlockMetrics> {
+    let mut analytics = Analytics::new(device, simulation);
+    for frame in 0..steps as usize {
+        params.frame = frame as u32;
+        simulation.set_params(queue, params);
+        let mut encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Sweep Encoder"),
+            }
+        );
+        simulation.step(queue, &mut encoder, frame, params.dt, None);
+        if frame + 1 == steps as usize {
+            analytics.record(device, &mut encoder, frame, None);
+            analytics.flush(device, &mut encoder);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        device.poll(wgpu::Maintain::Poll);
+    }
+
+    analytics.map();
+    device.poll(wgpu::Maintain::Wait);
+    analytics.read();
+    analytics.history().back()
+        .map(|&(_, metrics)| metrics)
+        .ok_or_else(|| anyhow::anyhow!("the flock metrics were never read back"))
+}
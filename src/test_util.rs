@@ -0,0 +1,35 @@
+// Helpers shared by the tests of the file formats.
+use crate::boid::Boid;
+
+// A boid's position and velocity, for comparing with what was written.
+pub fn components(boid: &Boid) -> [f32; 4] {
+    bytemuck::cast(*boid)
+}
+
+// A directory of its own for a test to write to, removed when it's dropped.
+pub struct TempPath(std::path::PathBuf);
+
+impl TempPath {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("wgpu_boids_{}_{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn join(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+
+    // Writes `contents` to `name` in the directory, giving its path.
+    pub fn write(&self, name: &str, contents: &str) -> String {
+        let path = self.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,553 @@
+use wgpu::util::DeviceExt;
+
+use crate::boid::{Boid, BoidTraits};
+use crate::fullscreen::create_fullscreen_pipeline;
+use crate::msaa::Multisample;
+use crate::culling::HISTORY_ARGS;
+
+// Fading trails behind the boids, either by never clearing the frame and fading it
+// instead, or by keeping the last `HISTORY_LENGTH` positions of every boid around and
+// drawing them as lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrailMode {
+    Off,
+    Accumulate,
+    History,
+}
+
+impl TrailMode {
+    pub const ALL: [TrailMode; 3] = [TrailMode::Off, TrailMode::Accumulate, TrailMode::History];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TrailMode::Off => "off",
+            TrailMode::Accumulate => "accumulate",
+            TrailMode::History => "history",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TrailUniform {
+    head: u32,
+    length: u32,
+    fill: u32,
+    _padding: u32,
+}
+
+const HISTORY_LENGTH: u32 = 32;
+
+// Each trail is a line list joining consecutive positions.
+pub const HISTORY_VERTICES: u32 = 2 * (HISTORY_LENGTH - 1);
+
+// Fraction of the accumulated image faded back to the clear colour every frame.
+const ACCUMULATION_FADE: f32 = 0.08;
+
+pub struct Trails {
+    mode: TrailMode,
+    n_boids: u32,
+
+    accumulation_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    blit_bind_group_layout: wgpu::BindGroupLayout,
+    blit_bind_group: wgpu::BindGroup,
+    blit_pipeline: wgpu::RenderPipeline,
+    fade_bind_group: wgpu::BindGroup,
+    fade_pipeline: wgpu::RenderPipeline,
+
+    uniform: TrailUniform,
+    uniform_buffer: wgpu::Buffer,
+    record_bind_groups: Vec<wgpu::BindGroup>,
+    record_pipeline: wgpu::ComputePipeline,
+    history_bind_group: wgpu::BindGroup,
+    history_pipeline: wgpu::RenderPipeline,
+}
+
+impl Trails {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        multisample: wgpu::MultisampleState,
+        boids_buffers: &[wgpu::Buffer],
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        clear_color: wgpu::Color,
+    ) -> Self {
+        let n_boids = (boids_buffers[0].size() / std::mem::size_of::<Boid>() as u64) as u32;
+        let fullscreen_shader = device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("blit.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(include_str!("fullscreen.wgsl"), include_str!("blit.wgsl")).into()),
+            }
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        let blit_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Blit Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            }
+        );
+
+        let accumulation_view = create_accumulation_view(device, config);
+        let blit_bind_group = create_blit_bind_group(device, &blit_bind_group_layout, &accumulation_view, &sampler);
+
+        let blit_pipeline = create_fullscreen_pipeline(
+            device,
+            "Blit Pipeline",
+            &blit_bind_group_layout,
+            &fullscreen_shader,
+            "fs_blit",
+            wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            wgpu::MultisampleState::default(),
+        );
+
+        let fade_color = [
+            clear_color.r as f32,
+            clear_color.g as f32,
+            clear_color.b as f32,
+            ACCUMULATION_FADE,
+        ];
+        let fade_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Fade Color Buffer"),
+                contents: bytemuck::cast_slice(&fade_color),
+                usage: wgpu::BufferUsages::UNIFORM,
+            }
+        );
+        let fade_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Fade Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }
+        );
+        let fade_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Fade Bind Group"),
+                layout: &fade_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: fade_buffer.as_entire_binding(),
+                    },
+                ],
+            }
+        );
+        let fade_pipeline = create_fullscreen_pipeline(
+            device,
+            "Fade Pipeline",
+            &fade_bind_group_layout,
+            &fullscreen_shader,
+            "fs_fade",
+            wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            multisample,
+        );
+
+
+        let uniform = TrailUniform { head: 0, length: HISTORY_LENGTH, fill: 1, _padding: 0 };
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Trail Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let history_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Trail History Buffer"),
+                size: (n_boids * HISTORY_LENGTH) as u64 * std::mem::size_of::<[f32; 2]>() as u64,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }
+        );
+
+        let record_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Trail Record Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }
+        );
+        let record_bind_groups = boids_buffers.iter().enumerate()
+            .map(|(i, boids_buffer)| device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    label: Some(format!("Trail Record Bind Group {}", i).as_str()),
+                    layout: &record_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: boids_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: history_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: uniform_buffer.as_entire_binding(),
+                        },
+                    ],
+                }
+            ))
+            .collect();
+
+        let record_shader = device.create_shader_module(wgpu::include_wgsl!("trails_record.wgsl"));
+        let record_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Trail Record Pipeline Layout"),
+                bind_group_layouts: &[
+                    &record_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+        let record_pipeline = device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("Trail Record Pipeline"),
+                layout: Some(&record_pipeline_layout),
+                module: &record_shader,
+                entry_point: "cs_record",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            }
+        );
+
+        let history_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Trail History Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }
+        );
+        let history_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Trail History Bind Group"),
+                layout: &history_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: history_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            }
+        );
+
+        let history_shader = device.create_shader_module(wgpu::include_wgsl!("trails.wgsl"));
+        let history_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Trail History Pipeline Layout"),
+                bind_group_layouts: &[
+                    camera_bind_group_layout,
+                    &history_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+        let history_pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Trail History Pipeline"),
+                layout: Some(&history_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &history_shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<BoidTraits>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x4],
+                        },
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &history_shader,
+                    entry_point: "fs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[
+                        Some(wgpu::ColorTargetState {
+                            format: config.format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }),
+                    ],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample,
+                multiview: None,
+                cache: None,
+            }
+        );
+
+        Self {
+            mode: TrailMode::Off,
+            n_boids,
+
+            accumulation_view,
+            sampler,
+            blit_bind_group_layout,
+            blit_bind_group,
+            blit_pipeline,
+            fade_bind_group,
+            fade_pipeline,
+
+            uniform,
+            uniform_buffer,
+            record_bind_groups,
+            record_pipeline,
+            history_bind_group,
+            history_pipeline,
+        }
+    }
+
+    pub fn mode(&self) -> TrailMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TrailMode) {
+        // positions recorded before the trails were last turned off are stale
+        if mode == TrailMode::History {
+            self.clear();
+        }
+        self.mode = mode;
+    }
+
+    // Forgets the recorded positions, for when the boids jump somewhere new.
+    pub fn clear(&mut self) {
+        self.uniform.fill = 1;
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.accumulation_view = create_accumulation_view(device, config);
+        self.blit_bind_group = create_blit_bind_group(device, &self.blit_bind_group_layout, &self.accumulation_view, &self.sampler);
+    }
+
+    // Records the positions in `boids_buffers[buffer]` into the history, if it's in use.
+    pub fn record(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, buffer: usize) {
+        if self.mode != TrailMode::History { return; }
+
+        self.uniform.head = (self.uniform.head + 1) % HISTORY_LENGTH;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+        self.uniform.fill = 0;
+
+        let mut compute_pass = encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("Trail Record Pass"),
+                timestamp_writes: None,
+            }
+        );
+        compute_pass.set_pipeline(&self.record_pipeline);
+        compute_pass.set_bind_group(0, &self.record_bind_groups[buffer], &[]);
+        compute_pass.dispatch_workgroups(self.n_boids.div_ceil(64), 1, 1);
+    }
+
+    // Where the boids should be drawn this frame, if not straight to the surface.
+    pub fn accumulation_target(&self) -> Option<&wgpu::TextureView> {
+        (self.mode == TrailMode::Accumulate).then_some(&self.accumulation_view)
+    }
+
+    // With MSAA, what's faded is the multisampled target, which still holds last frame's
+    // boids, and the result is resolved into the accumulation texture.
+    pub fn fade(&self, encoder: &mut wgpu::CommandEncoder, msaa: &Multisample) {
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("Trail Fade Pass"),
+                color_attachments: &[
+                    Some(msaa.attachment(&self.accumulation_view, wgpu::LoadOp::Load)),
+                ],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            }
+        );
+        render_pass.set_pipeline(&self.fade_pipeline);
+        render_pass.set_bind_group(0, &self.fade_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    pub fn blit(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("Trail Blit Pass"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        }
+                    })
+                ],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            }
+        );
+        render_pass.set_pipeline(&self.blit_pipeline);
+        render_pass.set_bind_group(0, &self.blit_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    // Draws the history lines of the live boids into a pass that already has the camera
+    // bound at group 0. `draw_args` are `Culling`'s.
+    pub fn render_history(&self, render_pass: &mut wgpu::RenderPass, traits_buffer: &wgpu::Buffer, draw_args: &wgpu::Buffer) {
+        if self.mode != TrailMode::History { return; }
+
+        render_pass.set_pipeline(&self.history_pipeline);
+        render_pass.set_bind_group(1, &self.history_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, traits_buffer.slice(..));
+        render_pass.draw_indirect(draw_args, HISTORY_ARGS);
+    }
+}
+
+fn create_accumulation_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::TextureView {
+    let texture = device.create_texture(
+        &wgpu::TextureDescriptor {
+            label: Some("Trail Accumulation Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        }
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_blit_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        }
+    )
+}
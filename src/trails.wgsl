@@ -0,0 +1,48 @@
+struct Trail {
+    head: u32,
+    length: u32,
+    fill: u32,
+}
+
+struct TrailInstance {
+    @location(0) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera_mat: mat3x3<f32>;
+
+@group(1) @binding(0) var<storage, read> history: array<vec2<f32>>;
+@group(1) @binding(1) var<uniform> trail: Trail;
+
+// One instance per boid, drawn as a line list where segment `k` joins the positions
+// `k` and `k + 1` steps old.
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) boid: u32,
+    instance: TrailInstance,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let age = vertex_index / 2u + vertex_index % 2u;
+    let slot = (trail.head + trail.length - age) % trail.length;
+    let pos = history[boid * trail.length + slot];
+
+    let clip_position = camera_mat * vec3<f32>(pos, 1.0);
+    out.clip_position = vec4<f32>(clip_position.xy, 0.0, 1.0);
+
+    let fade = 1.0 - f32(age) / f32(trail.length - 1u);
+    out.color = vec4<f32>(instance.color.rgb, instance.color.a * fade * 0.6);
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
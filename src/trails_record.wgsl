@@ -0,0 +1,31 @@
+struct Boid {
+    pos: vec2<f32>,
+    vel: vec2<f32>,
+}
+
+struct Trail {
+    head: u32,
+    length: u32,
+    fill: u32,
+}
+
+@group(0) @binding(0) var<storage, read> boids: array<Boid>;
+@group(0) @binding(1) var<storage, read_write> history: array<vec2<f32>>;
+@group(0) @binding(2) var<uniform> trail: Trail;
+
+// Writes every boid's position into slot `head` of its ring of `length` positions, or
+// into the whole ring when `fill` is set so a fresh trail doesn't streak from the origin.
+@compute
+@workgroup_size(64)
+fn cs_record(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let idx = global_invocation_id.x;
+    if(idx >= arrayLength(&boids)) { return; }
+
+    let pos = boids[idx].pos;
+    let ring = idx * trail.length;
+    if(trail.fill != 0) {
+        for(var k = u32(0); k < trail.length; k++) { history[ring + k] = pos; }
+    } else {
+        history[ring + trail.head] = pos;
+    }
+}
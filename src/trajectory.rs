@@ -0,0 +1,750 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::boid::{Boid, BoidTraits};
+use crate::readback::Readback;
+use crate::simulation::Simulation;
+
+// How boids are stored in a trajectory file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    // as they are on the GPU, 16 bytes a boid
+    Raw,
+    // every component rounded to 16 bits over a fixed range, 8 bytes a boid
+    Quantised,
+    // quantised, but every frame after the first of a chunk only stores how much each
+    // component changed since the frame before, in as few bytes as it fits, which is
+    // usually 4 to 6 a boid
+    Delta,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [
+        Encoding::Raw,
+        Encoding::Quantised,
+        Encoding::Delta,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Raw => "raw",
+            Encoding::Quantised => "quantised",
+            Encoding::Delta => "delta",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.name() == name)
+    }
+}
+
+// A trajectory file is a header followed by chunks of frames, all little endian:
+//
+//     header: "BOIDTRAJ", version u32, capacity u32, steps between frames u32,
+//             encoding u32, flags u32, position extent f32, velocity extent f32,
+//             then the traits of all `capacity` boids
+//     chunk:  frame count u32, byte length u32 of the frames that follow
+//     frame:  step u32, population u32, the live boids' traits if the flags say
+//             they're in every frame, then the live boids
+//
+// Chunks start over from a whole frame, so seeking only decodes from the start of one.
+const MAGIC: &[u8; 8] = b"BOIDTRAJ";
+const VERSION: u32 = 1;
+const TRAITS_PER_FRAME: u32 = 1;
+const CHUNK_FRAMES: usize = 32;
+
+// Quantised components are clamped to these, which is past the wall at its widest and
+// faster than any species flies.
+const POS_EXTENT: f32 = 4096.0;
+const VEL_EXTENT: f32 = 8.0;
+
+// Frames being read back before recording waits for the GPU to catch up.
+const MAX_IN_FLIGHT: usize = 4;
+
+// One recorded frame, decoded.
+pub struct Frame {
+    pub step: u32,
+    // only when the boids' traits can change during a run, when they're in every frame
+    pub traits: Option<Vec<BoidTraits>>,
+    // the live boids
+    pub boids: Vec<Boid>,
+}
+
+fn quantise(boid: &Boid) -> [i16; 4] {
+    let [x, y, vx, vy]: [f32; 4] = bytemuck::cast(*boid);
+    let q = |value: f32, extent: f32| ((value / extent).clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+    [q(x, POS_EXTENT), q(y, POS_EXTENT), q(vx, VEL_EXTENT), q(vy, VEL_EXTENT)]
+}
+
+fn dequantise(q: [i16; 4], pos_extent: f32, vel_extent: f32) -> Boid {
+    let d = |value: i16, extent: f32| value as f32 / i16::MAX as f32 * extent;
+    Boid::new(d(q[0], pos_extent), d(q[1], pos_extent), d(q[2], vel_extent), d(q[3], vel_extent))
+}
+
+// Zigzag encodes `value` so small magnitudes of either sign stay small, then writes it
+// seven bits a byte.
+fn write_varint(bytes: &mut Vec<u8>, value: i32) {
+    let mut v = ((value << 1) ^ (value >> 31)) as u32;
+    while v >= 0x80 {
+        bytes.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    bytes.push(v as u8);
+}
+
+// Reads values off the front of a slice, failing rather than panicking if it's too short.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        anyhow::ensure!(len <= self.bytes.len(), "the trajectory is truncated");
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> anyhow::Result<i16> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn varint(&mut self) -> anyhow::Result<i32> {
+        let mut v = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.take(1)?[0];
+            v |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((v >> 1) as i32 ^ -((v & 1) as i32));
+            }
+        }
+        anyhow::bail!("the trajectory has an overlong number")
+    }
+
+    fn pods<T: bytemuck::Pod>(&mut self, count: usize) -> anyhow::Result<Vec<T>> {
+        let size = std::mem::size_of::<T>();
+        Ok(self.take(count * size)?.chunks_exact(size).map(bytemuck::pod_read_unaligned).collect())
+    }
+}
+
+// Encodes frames after a trajectory's header and writes them out in whole chunks.
+struct Writer<W: Write> {
+    file: W,
+    encoding: Encoding,
+
+    // the frames of the chunk being filled, encoded
+    chunk: Vec<u8>,
+    chunk_frames: u32,
+    // the last frame added to the chunk, quantised, for delta encoding
+    previous: Vec<[i16; 4]>,
+}
+
+impl<W: Write> Writer<W> {
+    // Writes the header for `capacity` boids with `traits` to start with, which are then
+    // in every frame too if `traits_per_frame`.
+    fn new(
+        mut file: W,
+        capacity: usize,
+        every: usize,
+        encoding: Encoding,
+        traits_per_frame: bool,
+        traits: &[BoidTraits],
+    ) -> std::io::Result<Self> {
+        file.write_all(MAGIC)?;
+        let flags = if traits_per_frame { TRAITS_PER_FRAME } else { 0 };
+        for value in [VERSION, capacity as u32, every as u32, encoding as u32, flags] {
+            file.write_all(&value.to_le_bytes())?;
+        }
+        file.write_all(&POS_EXTENT.to_le_bytes())?;
+        file.write_all(&VEL_EXTENT.to_le_bytes())?;
+        file.write_all(bytemuck::cast_slice(traits))?;
+        Ok(Self {
+            file,
+            encoding,
+
+            chunk: Vec::new(),
+            chunk_frames: 0,
+            previous: Vec::new(),
+        })
+    }
+
+    fn add_frame(&mut self, step: u32, boids: &[Boid], traits: Option<&[BoidTraits]>) -> std::io::Result<()> {
+        self.chunk.extend(step.to_le_bytes());
+        self.chunk.extend((boids.len() as u32).to_le_bytes());
+        if let Some(traits) = traits {
+            self.chunk.extend(bytemuck::cast_slice(traits));
+        }
+        match self.encoding {
+            Encoding::Raw => self.chunk.extend(bytemuck::cast_slice(boids)),
+            Encoding::Quantised => for boid in boids {
+                for q in quantise(boid) {
+                    self.chunk.extend(q.to_le_bytes());
+                }
+            }
+            Encoding::Delta => {
+                let quantised: Vec<[i16; 4]> = boids.iter().map(quantise).collect();
+                for (i, q) in quantised.iter().enumerate() {
+                    match self.chunk_frames {
+                        0 => for component in q {
+                            self.chunk.extend(component.to_le_bytes());
+                        }
+                        _ => {
+                            // boids that weren't alive in the last frame start from zero
+                            let previous = self.previous.get(i).copied().unwrap_or_default();
+                            for (component, previous) in q.iter().zip(previous) {
+                                write_varint(&mut self.chunk, *component as i32 - previous as i32);
+                            }
+                        }
+                    }
+                }
+                self.previous = quantised;
+            }
+        }
+        self.chunk_frames += 1;
+        if self.chunk_frames as usize == CHUNK_FRAMES {
+            self.write_chunk()?;
+        }
+        Ok(())
+    }
+
+    fn write_chunk(&mut self) -> std::io::Result<()> {
+        if self.chunk_frames == 0 { return Ok(()); }
+        self.file.write_all(&self.chunk_frames.to_le_bytes())?;
+        self.file.write_all(&(self.chunk.len() as u32).to_le_bytes())?;
+        self.file.write_all(&self.chunk)?;
+        self.file.flush()?;
+        self.chunk.clear();
+        self.chunk_frames = 0;
+        Ok(())
+    }
+}
+
+// Writes every `every`th step of a 2D run to a trajectory file. The boids are copied
+// back from the GPU without waiting, unless too many copies are already in flight, and
+// are written in whole chunks.
+pub struct Recorder {
+    writer: Writer<BufWriter<File>>,
+    capacity: usize,
+    every: usize,
+    traits_per_frame: bool,
+
+    // copies of the boids, population and maybe traits, oldest first, with their step
+    in_flight: VecDeque<(u32, Readback)>,
+    free: Vec<Readback>,
+    capture_size: wgpu::BufferAddress,
+}
+
+impl Recorder {
+    // Records boids with traits from `traits` to start with. If they're changed during the
+    // run, by emitters or sinks, `traits_per_frame` stores them with every frame.
+    pub fn create(
+        path: &str,
+        simulation: &Simulation,
+        traits: &[BoidTraits],
+        every: usize,
+        encoding: Encoding,
+        traits_per_frame: bool,
+    ) -> std::io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let writer = Writer::new(file, simulation.capacity, every, encoding, traits_per_frame, traits)?;
+
+        let mut capture_size = simulation.boids_buffers[0].size() + simulation.population_buffer.size();
+        if traits_per_frame {
+            capture_size += simulation.traits_buffer.size();
+        }
+        Ok(Self {
+            writer,
+            capacity: simulation.capacity,
+            every,
+            traits_per_frame,
+
+            in_flight: VecDeque::new(),
+            free: Vec::new(),
+            capture_size,
+        })
+    }
+
+    // Records copying back the boids `frame`'s step left in `boids_buffers[(frame + 1) % 2]`,
+    // if it's one of the frames being kept.
+    pub fn record(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        simulation: &Simulation,
+        frame: usize,
+    ) -> std::io::Result<()> {
+        if !frame.is_multiple_of(self.every) { return Ok(()); }
+        if self.free.is_empty() && self.in_flight.len() >= MAX_IN_FLIGHT {
+            device.poll(wgpu::Maintain::Wait);
+            self.write()?;
+        }
+        let mut capture = self.free.pop()
+            .unwrap_or_else(|| Readback::new(device, "Trajectory Readback Buffer", self.capture_size));
+
+        let mut sources = vec![&simulation.boids_buffers[(frame + 1) % 2], &simulation.population_buffer];
+        if self.traits_per_frame {
+            sources.push(&simulation.traits_buffer);
+        }
+        capture.copy_many(encoder, &sources);
+        self.in_flight.push_back((frame as u32, capture));
+        Ok(())
+    }
+
+    // Call once the commands from `record` have been submitted.
+    pub fn map(&mut self) {
+        for (_, capture) in &mut self.in_flight {
+            capture.map();
+        }
+    }
+
+    // Adds the frames that have been read back so far, in order.
+    pub fn write(&mut self) -> std::io::Result<()> {
+        while let Some((step, capture)) = self.in_flight.front_mut() {
+            // read as words, so the boids and traits in it are aligned
+            let Some(words) = capture.read::<u32>() else { break };
+            let step = *step;
+            let boid_words = std::mem::size_of::<Boid>() / 4;
+            let population = (words[self.capacity * boid_words] as usize).min(self.capacity);
+            let boids: &[Boid] = bytemuck::cast_slice(&words[..population * boid_words]);
+            let traits = self.traits_per_frame.then(|| {
+                let start = self.capacity * boid_words + 1;
+                let traits: &[BoidTraits] = bytemuck::cast_slice(&words[start..]);
+                &traits[..population]
+            });
+            self.writer.add_frame(step, boids, traits)?;
+            let (_, capture) = self.in_flight.pop_front().unwrap();
+            self.free.push(capture);
+        }
+        Ok(())
+    }
+
+    // Waits for the frames still being read back and writes out everything left.
+    pub fn finish(mut self, device: &wgpu::Device) -> std::io::Result<()> {
+        device.poll(wgpu::Maintain::Wait);
+        self.write()?;
+        self.writer.write_chunk()
+    }
+}
+
+struct Chunk {
+    // where its frames start in the file
+    offset: usize,
+    len: usize,
+    // the index of its first frame in the whole trajectory
+    first: usize,
+    frames: usize,
+}
+
+// Plays a trajectory file back into a `Simulation`'s buffers, so it's drawn as if it
+// were being simulated. The play head is in recorded frames and moves `speed` steps of
+// the original run for every frame drawn.
+pub struct Replay {
+    capacity: usize,
+    every: usize,
+    encoding: Encoding,
+    traits_per_frame: bool,
+    pos_extent: f32,
+    vel_extent: f32,
+    traits: Vec<BoidTraits>,
+
+    data: Vec<u8>,
+    chunks: Vec<Chunk>,
+    frame_count: usize,
+    // the most boids alive in any frame
+    max_population: usize,
+    // the index of the chunk that's decoded, and its frames
+    decoded: (usize, Vec<Frame>),
+
+    position: f32,
+    // the index and step of the frame in the buffers, if any
+    shown: Option<(usize, u32)>,
+    pub playing: bool,
+    pub reverse: bool,
+    pub speed: f32,
+}
+
+impl Replay {
+    // Every chunk is decoded once here, so a damaged file is caught before playing it.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    fn from_bytes(data: Vec<u8>) -> anyhow::Result<Self> {
+        let mut reader = Reader { bytes: &data };
+        anyhow::ensure!(reader.take(MAGIC.len())? == MAGIC, "not a trajectory file");
+        let version = reader.u32()?;
+        anyhow::ensure!(version == VERSION, "unsupported trajectory version {version}");
+        let capacity = reader.u32()? as usize;
+        let every = reader.u32()? as usize;
+        let encoding = reader.u32()?;
+        let encoding = *Encoding::ALL.get(encoding as usize)
+            .ok_or_else(|| anyhow::anyhow!("unknown trajectory encoding {encoding}"))?;
+        let flags = reader.u32()?;
+        let pos_extent = reader.f32()?;
+        let vel_extent = reader.f32()?;
+        anyhow::ensure!(capacity > 0 && every > 0, "the trajectory's header is invalid");
+        let traits = reader.pods(capacity)?;
+
+        let mut replay = Self {
+            capacity,
+            every,
+            encoding,
+            traits_per_frame: flags & TRAITS_PER_FRAME != 0,
+            pos_extent,
+            vel_extent,
+            traits,
+
+            data: Vec::new(),
+            chunks: Vec::new(),
+            frame_count: 0,
+            max_population: 0,
+            decoded: (0, Vec::new()),
+
+            position: 0.0,
+            shown: None,
+            playing: true,
+            reverse: false,
+            speed: 1.0,
+        };
+
+        while !reader.bytes.is_empty() {
+            let frames = reader.u32()? as usize;
+            let len = reader.u32()? as usize;
+            let offset = data.len() - reader.bytes.len();
+            let decoded = replay.decode(reader.take(len)?, frames)?;
+            anyhow::ensure!(decoded.len() == frames, "a trajectory chunk has the wrong number of frames");
+            for frame in &decoded {
+                replay.max_population = replay.max_population.max(frame.boids.len());
+            }
+            replay.chunks.push(Chunk { offset, len, first: replay.frame_count, frames });
+            replay.frame_count += frames;
+        }
+        anyhow::ensure!(replay.frame_count > 0, "the trajectory has no frames");
+        replay.data = data;
+        replay.decoded = (0, replay.decode_chunk(0));
+        Ok(replay)
+    }
+
+    fn decode(&self, bytes: &[u8], frames: usize) -> anyhow::Result<Vec<Frame>> {
+        let mut reader = Reader { bytes };
+        let mut decoded: Vec<Frame> = Vec::with_capacity(frames);
+        let mut previous: Vec<[i16; 4]> = Vec::new();
+        for i in 0..frames {
+            let step = reader.u32()?;
+            let population = reader.u32()? as usize;
+            anyhow::ensure!(population <= self.capacity, "a trajectory frame has more boids than there's room for");
+            let traits = match self.traits_per_frame {
+                true => Some(reader.pods(population)?),
+                false => None,
+            };
+            let boids = match self.encoding {
+                Encoding::Raw => reader.pods(population)?,
+                Encoding::Quantised | Encoding::Delta => {
+                    let mut quantised = Vec::with_capacity(population);
+                    for b in 0..population {
+                        let mut q = [0i16; 4];
+                        match (self.encoding, i) {
+                            (Encoding::Delta, 1..) => {
+                                let last = previous.get(b).copied().unwrap_or_default();
+                                for (component, last) in q.iter_mut().zip(last) {
+                                    *component = (last as i32 + reader.varint()?) as i16;
+                                }
+                            }
+                            _ => for component in &mut q {
+                                *component = reader.i16()?;
+                            }
+                        }
+                        quantised.push(q);
+                    }
+                    let boids = quantised.iter().map(|&q| dequantise(q, self.pos_extent, self.vel_extent)).collect();
+                    previous = quantised;
+                    boids
+                }
+            };
+            decoded.push(Frame { step, traits, boids });
+        }
+        anyhow::ensure!(reader.bytes.is_empty(), "a trajectory chunk has bytes left over");
+        Ok(decoded)
+    }
+
+    fn decode_chunk(&self, index: usize) -> Vec<Frame> {
+        let chunk = &self.chunks[index];
+        self.decode(&self.data[chunk.offset..chunk.offset + chunk.len], chunk.frames)
+            .expect("chunks are checked when the trajectory is loaded")
+    }
+
+    // Frames are decoded a chunk at a time, so going through them in order is cheapest.
+    pub fn frame(&mut self, index: usize) -> &Frame {
+        let chunk = self.chunks.partition_point(|c| c.first + c.frames <= index);
+        if self.decoded.0 != chunk {
+            self.decoded = (chunk, self.decode_chunk(chunk));
+        }
+        &self.decoded.1[index - self.chunks[chunk].first]
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn max_population(&self) -> usize {
+        self.max_population
+    }
+
+    // The traits the boids start with, and keep unless they're in every frame.
+    pub fn initial_traits(&self) -> &[BoidTraits] {
+        &self.traits
+    }
+
+    // The boids and traits to create the simulation's buffers with.
+    pub fn initial_boids(&mut self) -> (Vec<Boid>, Vec<BoidTraits>) {
+        let capacity = self.capacity;
+        let mut boids = self.frame(0).boids.clone();
+        boids.resize(capacity, Boid::default());
+        (boids, self.traits.clone())
+    }
+
+    // The index of the frame being shown.
+    pub fn position(&self) -> usize {
+        self.position as usize
+    }
+
+    // The step of the original run the frame being shown was recorded at.
+    pub fn step(&self) -> u32 {
+        self.shown.map_or(0, |(_, step)| step)
+    }
+
+    pub fn seek(&mut self, index: usize) {
+        self.position = index.min(self.frame_count - 1) as f32;
+    }
+
+    // Moves the play head on for a frame drawn, and puts the frame it lands on in the
+    // simulation's buffers if it isn't there already.
+    pub fn update(&mut self, queue: &wgpu::Queue, simulation: &Simulation) {
+        if self.playing {
+            let direction = if self.reverse { -1.0 } else { 1.0 };
+            let last = (self.frame_count - 1) as f32;
+            self.position = (self.position + direction * self.speed / self.every as f32).clamp(0.0, last);
+            if self.position == 0.0 && self.reverse || self.position == last && !self.reverse {
+                self.playing = false;
+            }
+        }
+
+        let index = self.position();
+        if self.shown.is_some_and(|(shown, _)| shown == index) { return; }
+        let frame = self.frame(index);
+        let step = frame.step;
+        for buffer in &simulation.boids_buffers {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&frame.boids));
+        }
+        queue.write_buffer(&simulation.population_buffer, 0, bytemuck::cast_slice(&[frame.boids.len() as u32]));
+        if let Some(traits) = &frame.traits {
+            queue.write_buffer(&simulation.traits_buffer, 0, bytemuck::cast_slice(traits));
+        }
+        self.shown = Some((index, step));
+    }
+}
+
+#[cfg(test)]
+impl Replay {
+    // A raw encoded replay of `frames`, for testing what's done with one. The boids start
+    // with `traits`, and their traits are only in every frame if the frames have them.
+    pub fn from_frames(traits: &[BoidTraits], frames: &[Frame]) -> Self {
+        let traits_per_frame = frames.iter().any(|frame| frame.traits.is_some());
+        let mut writer = Writer::new(Vec::new(), traits.len(), 1, Encoding::Raw, traits_per_frame, traits).unwrap();
+        for frame in frames {
+            writer.add_frame(frame.step, &frame.boids, frame.traits.as_deref()).unwrap();
+        }
+        writer.write_chunk().unwrap();
+        Self::from_bytes(writer.file).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::boid::SPECIES;
+    use crate::test_util::components;
+
+    const CAPACITY: usize = 50;
+
+    fn random_boid(rng: &mut impl Rng) -> Boid {
+        let mut uniform = |extent: f32| extent * (2.0 * rng.random::<f32>() - 1.0);
+        Boid::new(uniform(600.0), uniform(600.0), uniform(1.5), uniform(1.5))
+    }
+
+    // Frames of boids moving a little each step, with the population changing as they
+    // go, across more than one chunk.
+    fn frames(rng: &mut impl Rng, count: usize) -> Vec<(u32, Vec<Boid>, Vec<BoidTraits>)> {
+        let mut boids: Vec<Boid> = (0..CAPACITY).map(|_| random_boid(rng)).collect();
+        (0..count)
+            .map(|i| {
+                for boid in &mut boids {
+                    let [x, y, vx, vy] = components(boid);
+                    *boid = Boid::new(x + vx, y + vy, vx, vy);
+                }
+                let population = rng.random_range(CAPACITY / 2..=CAPACITY);
+                let traits = (0..population).map(|_| *SPECIES.choose(rng).unwrap()).collect();
+                (3 * i as u32, boids[..population].to_vec(), traits)
+            })
+            .collect()
+    }
+
+    fn record(encoding: Encoding, traits_per_frame: bool, frames: &[(u32, Vec<Boid>, Vec<BoidTraits>)]) -> Vec<u8> {
+        let traits = vec![SPECIES[2]; CAPACITY];
+        let mut writer = Writer::new(Vec::new(), CAPACITY, 3, encoding, traits_per_frame, &traits).unwrap();
+        for (step, boids, traits) in frames {
+            writer.add_frame(*step, boids, traits_per_frame.then_some(&traits[..])).unwrap();
+        }
+        writer.write_chunk().unwrap();
+        writer.file
+    }
+
+    fn assert_round_trip(encoding: Encoding, traits_per_frame: bool, tolerance: [f32; 2]) {
+        let mut rng = StdRng::seed_from_u64(1);
+        let frames = frames(&mut rng, 2 * CHUNK_FRAMES + 5);
+        let mut replay = Replay::from_bytes(record(encoding, traits_per_frame, &frames)).unwrap();
+        assert_eq!(replay.frame_count(), frames.len());
+        assert_eq!(replay.capacity(), CAPACITY);
+        assert_eq!(replay.max_population(), frames.iter().map(|(_, boids, _)| boids.len()).max().unwrap());
+        assert!(replay.initial_traits().iter().all(|t| t.species() == SPECIES[2].species()));
+
+        // backwards, so every chunk is decoded again out of order
+        for (i, (step, boids, traits)) in frames.iter().enumerate().rev() {
+            let frame = replay.frame(i);
+            assert_eq!(frame.step, *step);
+            assert_eq!(frame.boids.len(), boids.len());
+            for (decoded, boid) in frame.boids.iter().zip(boids) {
+                for (k, (a, b)) in components(decoded).into_iter().zip(components(boid)).enumerate() {
+                    assert!((a - b).abs() <= tolerance[k / 2], "frame {i}: {a} isn't {b}");
+                }
+            }
+            match &frame.traits {
+                Some(decoded) => {
+                    let species = |traits: &[BoidTraits]| traits.iter().map(|t| t.species()).collect::<Vec<_>>();
+                    assert_eq!(species(decoded), species(traits));
+                }
+                None => assert!(!traits_per_frame),
+            }
+        }
+    }
+
+    // half a step of the quantisation, and a little for rounding
+    const QUANTISED: [f32; 2] = [POS_EXTENT / i16::MAX as f32 * 0.501, VEL_EXTENT / i16::MAX as f32 * 0.501];
+
+    #[test]
+    fn raw_round_trip_is_exact() {
+        assert_round_trip(Encoding::Raw, false, [0.0, 0.0]);
+        assert_round_trip(Encoding::Raw, true, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn quantised_round_trip() {
+        assert_round_trip(Encoding::Quantised, false, QUANTISED);
+        assert_round_trip(Encoding::Quantised, true, QUANTISED);
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        assert_round_trip(Encoding::Delta, false, QUANTISED);
+        assert_round_trip(Encoding::Delta, true, QUANTISED);
+    }
+
+    #[test]
+    fn delta_is_smaller_than_quantised() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let frames = frames(&mut rng, CHUNK_FRAMES);
+        assert!(record(Encoding::Delta, false, &frames).len() < record(Encoding::Quantised, false, &frames).len());
+    }
+
+    #[test]
+    fn quantising_clamps_to_the_extents() {
+        let boid = dequantise(quantise(&Boid::new(1e6, -1e6, 100.0, -100.0)), POS_EXTENT, VEL_EXTENT);
+        assert_eq!(components(&boid), [POS_EXTENT, -POS_EXTENT, VEL_EXTENT, -VEL_EXTENT]);
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        let values = [0, 1, -1, 63, -64, 64, -65, 8191, -8192, 65534, -65534, i32::MAX, i32::MIN];
+        let mut bytes = Vec::new();
+        for value in values {
+            write_varint(&mut bytes, value);
+        }
+        // zigzag keeps small magnitudes of either sign in a byte
+        assert_eq!(bytes[..5], [0, 2, 1, 126, 127]);
+        let mut reader = Reader { bytes: &bytes };
+        for value in values {
+            assert_eq!(reader.varint().unwrap(), value);
+        }
+        assert!(reader.bytes.is_empty());
+        assert!(Reader { bytes: &[0x80; 6] }.varint().is_err());
+    }
+
+    fn load_error(data: Vec<u8>) -> String {
+        match Replay::from_bytes(data) {
+            Ok(_) => panic!("the trajectory loaded"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn every_truncation_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for encoding in Encoding::ALL {
+            // a single chunk, so there's no shorter file that's still whole
+            let data = record(encoding, true, &frames(&mut rng, CHUNK_FRAMES));
+            for len in 0..data.len() {
+                load_error(data[..len].to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let data = record(Encoding::Delta, false, &frames(&mut rng, 4));
+        let patched = |offset: usize, value: u32| {
+            let mut data = data.clone();
+            data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            data
+        };
+        let mut magic = data.clone();
+        magic[0] = b'X';
+        assert_eq!(load_error(magic), "not a trajectory file");
+        assert_eq!(load_error(patched(8, 2)), "unsupported trajectory version 2");
+        assert_eq!(load_error(patched(20, 7)), "unknown trajectory encoding 7");
+        assert_eq!(load_error(patched(16, 0)), "the trajectory's header is invalid");
+
+        let header = 36 + CAPACITY * std::mem::size_of::<BoidTraits>();
+        assert_eq!(load_error(data[..header].to_vec()), "the trajectory has no frames");
+        assert_eq!(load_error(patched(header, 5)), "the trajectory is truncated");
+        assert_eq!(load_error(patched(header, 3)), "a trajectory chunk has bytes left over");
+        // a frame with more boids than the header has room for
+        assert_eq!(
+            load_error(patched(header + 12, CAPACITY as u32 + 1)),
+            "a trajectory frame has more boids than there's room for",
+        );
+    }
+
+    #[test]
+    fn encodings_have_names() {
+        for encoding in Encoding::ALL {
+            assert_eq!(Encoding::from_name(encoding.name()), Some(encoding));
+        }
+        assert_eq!(Encoding::from_name("zip"), None);
+    }
+}
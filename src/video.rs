@@ -0,0 +1,256 @@
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::JoinHandle;
+
+use crate::fullscreen::create_fullscreen_pipeline;
+use crate::readback::Readback;
+
+// Records the scene to a video by drawing it into an offscreen target of the video's size,
+// and piping every frame to ffmpeg as raw RGBA. The window just shows the target scaled
+// to fit. Frames are read back as they're drawn, waiting on the GPU and then on ffmpeg,
+// so none are ever dropped, and as the boids take one step a frame the video plays
+// smoothly however slowly it was recorded.
+pub struct Video {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    // the surface format is either, and ffmpeg is always given RGBA
+    bgra: bool,
+    // rows are padded to the copy alignment in the readback
+    padded_bytes_per_row: u32,
+    readback: Readback,
+
+    blit_pipeline: wgpu::RenderPipeline,
+    blit_bind_group: wgpu::BindGroup,
+
+    encoder: Child,
+    stdin: Option<ChildStdin>,
+    // drained as it goes, so ffmpeg never blocks on it, for reporting why it failed
+    stderr: Option<JoinHandle<String>>,
+    frames: usize,
+}
+
+impl Video {
+    // Starts ffmpeg encoding a video of `width` by `height` pixels at `fps` frames a
+    // second to `path`, in whatever format its extension names.
+    pub fn start(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        path: &str,
+        [width, height]: [u32; 2],
+        fps: u32,
+    ) -> anyhow::Result<Self> {
+        let bgra = match format.remove_srgb_suffix() {
+            wgpu::TextureFormat::Rgba8Unorm => false,
+            wgpu::TextureFormat::Bgra8Unorm => true,
+            format => anyhow::bail!("can't record video from a {format:?} surface"),
+        };
+        let max = device.limits().max_texture_dimension_2d;
+        anyhow::ensure!(width <= max && height <= max, "videos can be at most {max} pixels across");
+
+        let texture = device.create_texture(
+            &wgpu::TextureDescriptor {
+                label: Some("Video Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            }
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let padded_bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = Readback::new(device, "Video Readback Buffer", (padded_bytes_per_row * height) as wgpu::BufferAddress);
+
+        let shader = device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("blit.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(include_str!("fullscreen.wgsl"), include_str!("blit.wgsl")).into()),
+            }
+        );
+        let sampler = device.create_sampler(
+            &wgpu::SamplerDescriptor {
+                label: Some("Video Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }
+        );
+        let blit_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Video Blit Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            }
+        );
+        let blit_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Video Blit Bind Group"),
+                layout: &blit_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            }
+        );
+        let blit_pipeline = create_fullscreen_pipeline(
+            device,
+            "Video Blit Pipeline",
+            &blit_bind_group_layout,
+            &shader,
+            "fs_blit",
+            wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            wgpu::MultisampleState::default(),
+        );
+
+        let mut encoder = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{width}x{height}"), "-r", &fps.to_string()])
+            .args(["-i", "-", "-pix_fmt", "yuv420p", path])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| anyhow::anyhow!("failed to start ffmpeg: {err}"))?;
+        let stdin = encoder.stdin.take();
+        let stderr = encoder.stderr.take().map(|mut stderr| std::thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stderr.read_to_string(&mut output);
+            output
+        }));
+        log::info!("recording {width}x{height} video at {fps} fps to {path:?}");
+
+        Ok(Self {
+            texture,
+            view,
+            bgra,
+            padded_bytes_per_row,
+            readback,
+
+            blit_pipeline,
+            blit_bind_group,
+
+            encoder,
+            stdin,
+            stderr,
+            frames: 0,
+        })
+    }
+
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        let size = self.texture.size();
+        winit::dpi::PhysicalSize::new(size.width, size.height)
+    }
+
+    // Where the scene is drawn instead of the window.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    // Draws the frame being recorded onto `view`, stretched over the whole of it.
+    pub fn blit(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: Some("Video Blit Pass"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        }
+                    })
+                ],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            }
+        );
+        render_pass.set_pipeline(&self.blit_pipeline);
+        render_pass.set_bind_group(0, &self.blit_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    // Records copying back the frame that's been drawn, to be written once the commands
+    // have been submitted.
+    pub fn capture(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.readback.copy_texture(encoder, &self.texture, self.padded_bytes_per_row);
+    }
+
+    // Waits for the captured frame and passes it on to ffmpeg. ffmpeg opens its output before
+    // reading anything, so a bad path or format has it exit and the first frame fail to
+    // write, and then it's waited on so the error can say why.
+    pub fn write(&mut self, device: &wgpu::Device) -> anyhow::Result<()> {
+        self.readback.map();
+        device.poll(wgpu::Maintain::Wait);
+        let Some(mut bytes) = self.readback.read::<u8>() else { return Ok(()) };
+        let row_bytes = self.texture.width() as usize * 4;
+        let mut rows = bytes.chunks_exact_mut(self.padded_bytes_per_row as usize);
+        let stdin = self.stdin.as_mut().expect("the video has already been finished");
+        let written = rows.try_for_each(|row| {
+            let row = &mut row[..row_bytes];
+            if self.bgra {
+                for pixel in row.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+            stdin.write_all(row)
+        });
+        if let Err(err) = written {
+            self.close()?;
+            anyhow::bail!("ffmpeg stopped taking frames: {err}");
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Closes ffmpeg's input and waits for it to finish the file.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.close()?;
+        log::info!("recorded {} frames of video", self.frames);
+        Ok(())
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        drop(self.stdin.take());
+        let status = self.encoder.wait()?;
+        let stderr = self.stderr.take().and_then(|reader| reader.join().ok()).unwrap_or_default();
+        anyhow::ensure!(status.success(), "ffmpeg exited with {status}: {}", stderr.trim());
+        Ok(())
+    }
+}